@@ -0,0 +1,73 @@
+use std::process::ExitCode;
+
+use exahost::compiler::cfg::Cfg;
+use exahost::compiler::{config::Config as CompilerConfig, Compiler};
+use exahost::config::HostConfig;
+
+const USAGE: &str = "usage: exacfg [--json] [--config <host config>] FILE
+
+prints the control-flow graph of an EXA source file as Graphviz DOT
+  --json    print JSON instead";
+
+fn main() -> ExitCode {
+    let mut json = false;
+    let mut config_path = "hosts/config.toml".to_string();
+    let mut file = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--json" => json = true,
+            "--config" => match args.next() {
+                Some(p) => config_path = p,
+                None => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::from(2);
+                }
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ if file.is_none() => file = Some(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                return ExitCode::from(2);
+            }
+        }
+    }
+    let file = match file {
+        Some(f) => f,
+        None => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let config = match HostConfig::from_file(&config_path) {
+        Some(config) => (*config.compiler_config).clone(),
+        None => CompilerConfig::extended(),
+    };
+    let program = match Compiler::new(config).compile_program_file(&file) {
+        Ok(p) => p,
+        Err(errs) => {
+            for e in errs {
+                eprintln!(
+                    "exacfg: {}:{}:{}: {:?}: {}",
+                    e.file.as_deref().unwrap_or(&file),
+                    e.row + 1,
+                    e.col + 1,
+                    e.etype,
+                    e.context
+                );
+            }
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let cfg = Cfg::new(&program);
+    match json {
+        true => println!("{:#}", cfg.to_json(&program)),
+        false => print!("{}", cfg.to_dot(&program)),
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,105 @@
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use exahost::compiler::{config::Config as CompilerConfig, Compiler};
+use exahost::config::HostConfig;
+
+const USAGE: &str = "usage: exafmt [--check] [--config <host config>] [FILE...]
+
+formats EXA source files in place, or stdin to stdout if no files are given
+  --check    don't write anything, exit with 1 if any file isn't formatted";
+
+fn main() -> ExitCode {
+    let mut check = false;
+    let mut config_path = "hosts/config.toml".to_string();
+    let mut files = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--check" => check = true,
+            "--config" => match args.next() {
+                Some(p) => config_path = p,
+                None => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::from(2);
+                }
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ => files.push(arg),
+        }
+    }
+
+    let config = match HostConfig::from_file(&config_path) {
+        Some(config) => (*config.compiler_config).clone(),
+        None => CompilerConfig::extended(),
+    };
+    let compiler = Compiler::new(config);
+
+    if files.is_empty() {
+        let mut source = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut source) {
+            eprintln!("exafmt: {}", e);
+            return ExitCode::from(2);
+        }
+        return match format(&compiler, &source) {
+            Ok(formatted) if check => match formatted == source {
+                true => ExitCode::SUCCESS,
+                false => ExitCode::FAILURE,
+            },
+            Ok(formatted) => {
+                let _ = io::stdout().write_all(formatted.as_bytes());
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("exafmt: <stdin>: {}", e);
+                ExitCode::from(2)
+            }
+        };
+    }
+
+    let mut status = ExitCode::SUCCESS;
+    for file in files {
+        let source = match std::fs::read_to_string(&file) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("exafmt: {}: {}", file, e);
+                status = ExitCode::from(2);
+                continue;
+            }
+        };
+        let formatted = match format(&compiler, &source) {
+            Ok(f) => f,
+            Err(e) => {
+                eprintln!("exafmt: {}: {}", file, e);
+                status = ExitCode::from(2);
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{}", file);
+            status = ExitCode::FAILURE;
+        } else if let Err(e) = std::fs::write(&file, formatted) {
+            eprintln!("exafmt: {}: {}", file, e);
+            status = ExitCode::from(2);
+        }
+    }
+    status
+}
+
+/// formats `source`, refusing if the result would compile differently
+fn format(compiler: &Compiler, source: &str) -> Result<String, String> {
+    let raw: Vec<&str> = source.lines().collect();
+    let formatted = compiler.format(&raw);
+    let formatted_raw: Vec<&str> = formatted.lines().collect();
+    match (compiler.compile(&raw), compiler.compile(&formatted_raw)) {
+        (Ok(a), Ok(b)) if a != b => Err("formatting would change the compiled program".into()),
+        (Ok(_), Err(_)) => Err("formatting would break compilation".into()),
+        _ => Ok(formatted),
+    }
+}
@@ -0,0 +1,26 @@
+use std::io;
+
+use exahost::compiler::{config::Config as CompilerConfig, Compiler};
+use exahost::config::HostConfig;
+use exahost::lsp::Server;
+
+fn main() {
+    let config = load_compiler_config();
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    if let Err(e) = Server::new(Compiler::new(config)).run(stdin.lock(), stdout.lock()) {
+        eprintln!("exahost-lsp: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// uses the compiler settings of the local host, so diagnostics match what it would accept
+fn load_compiler_config() -> CompilerConfig {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "hosts/config.toml".to_string());
+    match HostConfig::from_file(path) {
+        Some(config) => (*config.compiler_config).clone(),
+        None => CompilerConfig::extended(),
+    }
+}
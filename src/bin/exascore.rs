@@ -0,0 +1,37 @@
+use std::process::ExitCode;
+
+use exahost::metrics::{report, Run};
+
+const USAGE: &str = "usage: exascore RUN...
+
+compares runs saved as JSON, printing the cycles, size and activity of each";
+
+fn main() -> ExitCode {
+    let mut paths = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match &arg[..] {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    }
+
+    let mut runs = Vec::with_capacity(paths.len());
+    for path in paths {
+        match Run::load(&path) {
+            Some(run) => runs.push(run),
+            None => {
+                eprintln!("exascore: {}: not a saved run", path);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    print!("{}", report(&runs));
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,115 @@
+use std::path::Path;
+use std::process::ExitCode;
+
+use exahost::solution::{MemoryMode, Solution, SolutionExa};
+
+const USAGE: &str = "usage: exasolution import SOLUTION [DIR]
+       exasolution export [--puzzle <id>] [--name <name>] SOLUTION FILE...
+
+converts between EXAPUNKS .solution files and EXA source files
+  import    writes each exa of SOLUTION to DIR (default .) as NAME.exa,
+            starting with `@mode local` if the exa runs in local mode
+  export    packs FILEs into SOLUTION, each exa named by its file name,
+            in local mode if it has a `@mode local` directive";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(|a| &a[..]) {
+        Some("import") if (2..=3).contains(&args.len()) => {
+            import(&args[1], args.get(2).map_or(".", |d| &d[..]))
+        }
+        Some("export") => export(&args[1..]),
+        Some("-h" | "--help") => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn import(solution: &str, dir: &str) -> ExitCode {
+    let solution = match Solution::from_file(solution) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("exasolution: {}: {:?}", solution, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut files = Vec::with_capacity(solution.exas.len());
+    for exa in solution.exas.iter() {
+        match exa.file_name() {
+            Some(f) => files.push(f),
+            None => {
+                eprintln!(
+                    "exasolution: refusing to import an exa named {:?}",
+                    exa.name
+                );
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    for (exa, file) in solution.exas.iter().zip(files) {
+        let path = Path::new(dir).join(file);
+        if let Err(e) = std::fs::write(&path, exa.to_source()) {
+            eprintln!("exasolution: {}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+        match exa.mode {
+            MemoryMode::Global => println!("{}", path.display()),
+            MemoryMode::Local => println!("{} (local)", path.display()),
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn export(args: &[String]) -> ExitCode {
+    let mut puzzle = "PB000".to_string();
+    let mut name = "EXAHOST EXPORT".to_string();
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let target = match &arg[..] {
+            "--puzzle" => &mut puzzle,
+            "--name" => &mut name,
+            _ => {
+                paths.push(arg);
+                continue;
+            }
+        };
+        match args.next() {
+            Some(v) => *target = v.clone(),
+            None => {
+                eprintln!("{}", USAGE);
+                return ExitCode::from(2);
+            }
+        }
+    }
+    if paths.len() < 2 {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    }
+
+    let mut exas = Vec::with_capacity(paths.len() - 1);
+    for path in paths[1..].iter() {
+        let source = match std::fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("exasolution: {}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        };
+        let exa_name = Path::new(path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        exas.push(SolutionExa::from_source(&exa_name, &source));
+    }
+    if let Err(e) = Solution::new(&puzzle, &name, exas).save(paths[0]) {
+        eprintln!("exasolution: {}: {}", paths[0], e);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
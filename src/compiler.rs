@@ -1,22 +1,39 @@
-use regex::{Match, Regex};
+use regex::Regex;
 
-use crate::exa::{Arg, Instruction, OpCode};
+use crate::exa::{escape_keyword, Arg, Comp, Exa, Instruction, Metadata, OpCode, Requirement};
 use std::{
     collections::HashMap,
     fmt::Display,
-    ops::{Deref, DerefMut, Range},
+    io::Read,
+    ops::{Deref, DerefMut, RangeInclusive},
+    path::{Path, PathBuf},
+    rc::Rc,
 };
 
 use self::config::Config;
+use self::expression::Value;
 
+pub mod analysis;
+pub mod cfg;
 pub mod config;
+mod expression;
+mod formatter;
+mod highlight;
+pub mod optimizer;
+mod program;
+
+pub use highlight::{Span, SpanKind};
+pub use program::{Label, Program, SourceLocation};
 
 #[derive(Debug, Clone)]
 pub struct Token {
+    pub file: Option<Rc<str>>,
     pub row: usize,
     pub col: usize,
     pub content: String,
     pub ttype: TokenType,
+    /// `@call`s this token was expanded from, outermost first
+    pub expansion: Vec<CallSite>,
 }
 
 impl Token {
@@ -25,10 +42,12 @@ impl Token {
         T: Display,
     {
         Self {
+            file: None,
             row,
             col,
             content: content.to_string(),
             ttype,
+            expansion: Vec::new(),
         }
     }
 }
@@ -44,15 +63,20 @@ pub enum TokenType {
     MacroStart,
     MacroEnd,
     MacroReplace,
+    ConstDef,
     Comment,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Error {
+    /// file the error originates from, `None` for sources passed in directly
+    pub file: Option<Rc<str>>,
     pub row: usize,
     pub col: usize,
     pub context: String,
     pub etype: ErrorType,
+    /// `@call`s the erroneous code was expanded from, outermost first
+    pub expansion: Vec<CallSite>,
 }
 
 impl Error {
@@ -61,19 +85,23 @@ impl Error {
         T: Display,
     {
         Self {
+            file: None,
             row,
             col,
             context: content.to_string(),
             etype,
+            expansion: Vec::new(),
         }
     }
 
     fn from_token(t: Token, etype: ErrorType) -> Self {
         Self {
+            file: t.file,
             row: t.row,
             col: t.col,
             context: t.content,
             etype,
+            expansion: t.expansion,
         }
     }
 }
@@ -81,8 +109,10 @@ impl Error {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ErrorType {
     UnknownInstruction,
+    DisabledInstruction,
+    DisabledComparison,
     NumberOutOfRange,
-    NestedMacros,
+    MacroLevelOutOfRange,
     MissingRepTag,
     MissingEndTag,
     InvalidNumber,
@@ -92,10 +122,30 @@ pub enum ErrorType {
     SigLenMismatch,
     DuplicateLabel,
     UndefinedLabel,
+    MissingMacroName,
+    NestedDefinition,
+    DuplicateMacro,
+    UndefinedMacro,
+    RecursiveMacro,
+    MacroArgMismatch,
+    IncludeNotFound,
+    IncludeCycle,
+    UnreadableSource,
+    DuplicateDirective,
+    InvalidRequirement,
+    InvalidMode,
+    UnterminatedKeyword,
+    InvalidEscape,
+    KeywordTooLong,
+    ProgramTooLarge,
+    InvalidExpression,
+    InvalidConstant,
+    DuplicateConstant,
+    UndefinedConstant,
 }
 
 #[derive(Debug, Clone)]
-struct Signature(pub Vec<Vec<TokenType>>);
+pub struct Signature(pub Vec<Vec<TokenType>>);
 
 impl Signature {
     pub fn one(ttypes: &[TokenType]) -> Self {
@@ -155,6 +205,10 @@ impl Signature {
         }
         len
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -163,6 +217,11 @@ pub struct Compiler {
     comparisons: Vec<String>,
     keyword_delimiter: char,
     comment_prefixes: Vec<String>,
+    inline_comment_prefixes: Vec<String>,
+    hardware_registers: Vec<String>,
+    include_paths: Vec<String>,
+    optimize: bool,
+    max_program_size: usize,
     macro_regex: Regex,
 }
 
@@ -173,23 +232,17 @@ impl Compiler {
             comparisons: config.generate_comparisons(),
             keyword_delimiter: config.keyword_delimiter,
             comment_prefixes: config.comment_prefixes,
-            // disallow space in macro
-            //macro_regex: Regex::new(r"@\{-?\d{1,4}, ?-?\d{1,4}\}").unwrap(),
-            macro_regex: Regex::new(r"@\{-?\d{1,4},-?\d{1,4}\}").unwrap(),
+            inline_comment_prefixes: config.inline_comment_prefixes,
+            hardware_registers: config.hardware_registers,
+            include_paths: config.include_paths,
+            optimize: config.optimize,
+            max_program_size: usize::min(config.max_program_size, u16::MAX as usize),
+            macro_regex: Regex::new(r"@\{[^}]*\}").unwrap(),
         }
     }
 
     pub fn compile(&self, raw: &[&str]) -> Result<Box<[Instruction]>, Vec<Error>> {
-        let mut tokens = self.tokenize(raw);
-        tokens = self.expand_macros(tokens);
-        self.typecheck(&mut tokens);
-        Self::bake_jumps(&mut tokens);
-        let errs = Self::extract_errs(&tokens);
-        // convert to instruction
-        if !errs.is_empty() {
-            return Err(errs);
-        }
-        Ok(self.lines_to_instructions(tokens))
+        Ok(self.compile_source(None, raw)?.instructions)
     }
 
     fn lines_to_instructions(&self, lines: Vec<Line>) -> Box<[Instruction]> {
@@ -197,23 +250,14 @@ impl Compiler {
         for line in lines.into_iter() {
             let op: OpCode = line[0].clone().unwrap().try_into().unwrap();
             let args: Vec<Arg> = line[1..]
-                .into_iter()
+                .iter()
                 .map(|t| self.token_to_arg(t.clone().unwrap()).unwrap())
                 .collect();
             instrs.push(Instruction(
                 op,
-                match args.get(0) {
-                    Some(a) => Some(a.clone()),
-                    None => None,
-                },
-                match args.get(1) {
-                    Some(a) => Some(a.clone()),
-                    None => None,
-                },
-                match args.get(2) {
-                    Some(a) => Some(a.clone()),
-                    None => None,
-                },
+                args.first().cloned(),
+                args.get(1).cloned(),
+                args.get(2).cloned(),
             ));
         }
         instrs.into_boxed_slice()
@@ -223,7 +267,9 @@ impl Compiler {
         match t.ttype {
             TokenType::Number => Ok(Arg::Number(t.content.parse().unwrap())),
             TokenType::Keyword => Ok(Arg::Keyword(
-                t.content.trim_matches(self.keyword_delimiter).into(),
+                parse_keyword(&t.content, self.keyword_delimiter)
+                    .unwrap()
+                    .into(),
             )),
             TokenType::JumpLabel => Ok(Arg::JumpIndex(t.content.parse().unwrap())),
             TokenType::Comparison => Ok(Arg::Comp(t.content.parse().unwrap())),
@@ -232,8 +278,247 @@ impl Compiler {
             | TokenType::Comment
             | TokenType::MacroEnd
             | TokenType::MacroStart
-            | TokenType::MacroReplace => Err(Error::from_token(t, ErrorType::NotArg)),
+            | TokenType::MacroReplace
+            | TokenType::ConstDef => Err(Error::from_token(t, ErrorType::NotArg)),
+        }
+    }
+
+    pub fn compile_file<P>(&self, path: P) -> Result<Box<[Instruction]>, Vec<Error>>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(self.compile_program_file(path)?.instructions)
+    }
+
+    /// compiles a whole source text, which may use CRLF line endings and start with a BOM
+    pub fn compile_str(&self, source: &str) -> Result<Box<[Instruction]>, Vec<Error>> {
+        Ok(self
+            .compile_source(None, &source_lines(source))?
+            .instructions)
+    }
+
+    pub fn compile_reader<R: Read>(&self, mut reader: R) -> Result<Box<[Instruction]>, Vec<Error>> {
+        let mut source = String::new();
+        if let Err(e) = reader.read_to_string(&mut source) {
+            return Err(vec![Error::new(0, 0, e, ErrorType::UnreadableSource)]);
+        }
+        self.compile_str(&source)
+    }
+
+    /// compiles `raw`, keeping the source map and labels around for further analysis
+    pub fn compile_program(&self, raw: &[&str]) -> Result<Program, Vec<Error>> {
+        self.compile_source(None, raw)
+    }
+
+    /// compiles the file at `path`, errors carry its path
+    pub fn compile_program_file<P>(&self, path: P) -> Result<Program, Vec<Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let file: Rc<str> = path.display().to_string().into();
+        let content = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                let mut err = Error::new(0, 0, e, ErrorType::UnreadableSource);
+                err.file = Some(file);
+                return Err(vec![err]);
+            }
+        };
+        self.compile_source(Some(file), &source_lines(&content))
+    }
+
+    /// compiles `raw` as the contents of the file at `path`, whether or not it's saved there,
+    /// so includes resolve relative to it and errors carry its path
+    pub fn compile_program_at<P>(&self, path: P, raw: &[&str]) -> Result<Program, Vec<Error>>
+    where
+        P: AsRef<Path>,
+    {
+        self.compile_source(Some(path.as_ref().display().to_string().into()), raw)
+    }
+
+    /// compiles the file at `path` into an exa,
+    /// named by its `@name` directive, or else by its file name without the extension
+    pub fn compile_exa_file<P>(&self, path: P) -> Result<Exa, Vec<Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let program = self.compile_program_file(path)?;
+        let name = match &program.metadata.name {
+            Some(n) => n.clone(),
+            None => path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        };
+        Ok(Exa::from_program(&name, program))
+    }
+
+    /// compiles every `.exa` file in `dir` into an exa, in file name order
+    ///
+    /// errors from every file are collected before giving up
+    pub fn compile_dir<P>(&self, dir: P) -> Result<Vec<Exa>, Vec<Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let dir = dir.as_ref();
+        let mut paths: Vec<PathBuf> = match std::fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == "exa"))
+                .collect(),
+            Err(e) => {
+                let mut err = Error::new(0, 0, e, ErrorType::UnreadableSource);
+                err.file = Some(dir.display().to_string().into());
+                return Err(vec![err]);
+            }
+        };
+        paths.sort();
+        let mut exas = Vec::with_capacity(paths.len());
+        let mut errs = Vec::new();
+        for path in paths {
+            match self.compile_exa_file(&path) {
+                Ok(exa) => exas.push(exa),
+                Err(mut e) => errs.append(&mut e),
+            }
+        }
+        match errs.is_empty() {
+            true => Ok(exas),
+            false => Err(errs),
+        }
+    }
+
+    fn compile_source(&self, file: Option<Rc<str>>, raw: &[&str]) -> Result<Program, Vec<Error>> {
+        let mut tokens = self.tokenize(file, raw);
+        // comments are only kept around for tooling
+        tokens.retain(|l| !l.is_empty());
+        let (metadata, entry) = self.extract_metadata(&mut tokens);
+        let constants;
+        (tokens, constants) = self.substitute_constants(tokens);
+        tokens = self.expand_macros(tokens, &constants);
+        self.typecheck(&mut tokens);
+        let (mut labels, source_map) = Self::bake_jumps(&mut tokens);
+        let mut errs = Self::extract_errs(&tokens);
+        if let Some(entry) = entry {
+            match labels.iter_mut().find(|l| l.name == entry.content) {
+                Some(label) => label.uses += 1,
+                None => errs.push(Error::from_token(entry, ErrorType::UndefinedLabel)),
+            }
+        }
+        if errs.is_empty() && tokens.len() > self.max_program_size {
+            let first_over = &tokens[self.max_program_size];
+            errs.push(first_over.error(
+                first_over.location().col,
+                format!(
+                    "{} instructions, limit is {}",
+                    tokens.len(),
+                    self.max_program_size
+                ),
+                ErrorType::ProgramTooLarge,
+            ));
+        }
+        // convert to instruction
+        if !errs.is_empty() {
+            return Err(errs);
+        }
+        let program = Program {
+            size: tokens.len(),
+            instructions: self.lines_to_instructions(tokens),
+            source_map,
+            labels,
+            metadata,
+        };
+        match self.optimize {
+            true => Ok(optimizer::optimize(program)),
+            false => Ok(program),
+        }
+    }
+
+    /// removes the `@name`, `@author`, `@description`, `@entry`, `@mode` and `@requires`
+    /// directives, returning the metadata they set, along with the `@entry` label to check once
+    /// labels are known
+    fn extract_metadata(&self, lines: &mut Vec<Line>) -> (Metadata, Option<Token>) {
+        let mut metadata = Metadata::default();
+        let mut entry = None;
+        let mut rest = Vec::with_capacity(lines.len());
+        for line in lines.drain(..) {
+            let directive = match line[0].as_ref() {
+                Ok(t) if METADATA_DIRECTIVES.contains(&&t.content.to_lowercase()[..]) => t.clone(),
+                _ => {
+                    rest.push(line);
+                    continue;
+                }
+            };
+            if line.has_error() {
+                rest.push(line);
+                continue;
+            }
+            let args: Vec<Token> = line[1..].iter().flatten().cloned().collect();
+            let kind = directive.content.to_lowercase();
+            let single_arg = matches!(&kind[..], "@name" | "@entry" | "@mode");
+            if args.is_empty() || (single_arg && args.len() != 1) {
+                let err = line.error(
+                    line.last_col(),
+                    format!("Expected 1 arg, found {}", args.len()),
+                    ErrorType::SigLenMismatch,
+                );
+                rest.push(err.into());
+                continue;
+            }
+            let text = args
+                .iter()
+                .map(|t| match t.ttype {
+                    TokenType::Keyword => parse_keyword(&t.content, self.keyword_delimiter)
+                        .unwrap_or_else(|_| t.content.clone()),
+                    _ => t.content.clone(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            let slot = match &kind[..] {
+                "@name" => &mut metadata.name,
+                "@author" => &mut metadata.author,
+                "@description" => &mut metadata.description,
+                "@entry" => &mut metadata.entry,
+                "@mode" => {
+                    let err = match text.parse() {
+                        Ok(_) if metadata.mode.is_some() => {
+                            Error::from_token(directive, ErrorType::DuplicateDirective)
+                        }
+                        Ok(mode) => {
+                            metadata.mode = Some(mode);
+                            continue;
+                        }
+                        Err(_) => Error::from_token(args[0].clone(), ErrorType::InvalidMode),
+                    };
+                    rest.push(err.into());
+                    continue;
+                }
+                _ => {
+                    for t in args {
+                        if t.content.starts_with('#') {
+                            let reg = t.content.to_uppercase();
+                            metadata.requires.push(Requirement::HardwareRegister(reg));
+                        } else if let Ok(op) = t.content.parse() {
+                            metadata.requires.push(Requirement::Instruction(op));
+                        } else {
+                            rest.push(Error::from_token(t, ErrorType::InvalidRequirement).into());
+                        }
+                    }
+                    continue;
+                }
+            };
+            if slot.is_some() {
+                rest.push(Error::from_token(directive, ErrorType::DuplicateDirective).into());
+                continue;
+            }
+            *slot = Some(text);
+            if kind == "@entry" {
+                entry = Some(args[0].clone());
+            }
         }
+        *lines = rest;
+        (metadata, entry)
     }
 
     fn extract_errs(lines: &[Line]) -> Vec<Error> {
@@ -244,16 +529,20 @@ impl Compiler {
             }
             for res in line.iter() {
                 match res {
-                    Ok(_) => (),
-                    Err(e) => errs.push(e.clone()),
+                    // a `@rep` repeats the errors in its body once per iteration
+                    Err(e) if !errs.contains(e) => errs.push(e.clone()),
+                    _ => (),
                 }
             }
         }
         errs
     }
 
-    fn bake_jumps(lines: &mut Vec<Line>) {
+    /// removes `MARK`s and replaces labels with instruction indices,
+    /// returning the labels and the source location of each remaining line
+    fn bake_jumps(lines: &mut Vec<Line>) -> (Vec<Label>, Box<[SourceLocation]>) {
         let mut label_map = HashMap::new();
+        let mut labels = Vec::new();
         let mut len = lines.len();
         let mut x = 0;
         while x < len {
@@ -272,8 +561,14 @@ impl Compiler {
                                 x += 1;
                                 continue;
                             }
-                            lines.remove(x);
-                            label_map.insert(label.content, x);
+                            let location = lines.remove(x).location();
+                            label_map.insert(label.content.clone(), labels.len());
+                            labels.push(Label {
+                                name: label.content,
+                                index: x,
+                                location,
+                                uses: 0,
+                            });
                             len -= 1;
                         }
                     }
@@ -284,6 +579,8 @@ impl Compiler {
                 }
             }
         }
+        // an index is usually shorter than its label, so spans are taken before replacing them
+        let source_map = lines.iter().map(|l| l.location()).collect();
         for line in lines.iter_mut() {
             if line.has_error() {
                 continue;
@@ -293,22 +590,28 @@ impl Compiler {
                     OpCode::Jump | OpCode::Fjmp | OpCode::Tjmp | OpCode::Repl => {
                         let label = line[1].clone().unwrap();
                         if label.ttype == TokenType::JumpLabel {
-                            if !label_map.contains_key(&label.content) {
-                                line.push(Err(Error::from_token(label, ErrorType::UndefinedLabel)));
-                                continue;
-                            }
-                            line[1] = Ok(Token::new(
-                                label.row,
-                                label.col,
-                                label_map.get(&label.content).unwrap().to_string(),
-                                TokenType::JumpLabel,
-                            ));
+                            let target = match label_map.get(&label.content) {
+                                Some(l) => &mut labels[*l],
+                                None => {
+                                    line.push(Err(Error::from_token(
+                                        label,
+                                        ErrorType::UndefinedLabel,
+                                    )));
+                                    continue;
+                                }
+                            };
+                            target.uses += 1;
+                            line[1] = Ok(Token {
+                                content: target.index.to_string(),
+                                ..label
+                            });
                         }
                     }
                     _ => continue,
                 }
             }
         }
+        (labels, source_map)
     }
 
     fn typecheck(&self, lines: &mut [Line]) {
@@ -323,105 +626,282 @@ impl Compiler {
                     continue;
                 }
             };
-            let sig = self.instruction_signatures.get(&op).unwrap();
+            for arg in line[1..].iter_mut() {
+                let err = match arg {
+                    Ok(t) if t.ttype == TokenType::Keyword => {
+                        parse_keyword(&t.content, self.keyword_delimiter)
+                            .err()
+                            .map(|etype| Error::from_token(t.clone(), etype))
+                    }
+                    _ => None,
+                };
+                if let Some(e) = err {
+                    *arg = Err(e);
+                }
+            }
+            let sig = match self.instruction_signatures.get(&op) {
+                Some(s) => s,
+                None => {
+                    line[0] = Err(Error::from_token(
+                        line[0].clone().unwrap(),
+                        ErrorType::DisabledInstruction,
+                    ));
+                    continue;
+                }
+            };
             {
                 let arg_slice = &mut line[1..];
                 for x in 0..usize::min(arg_slice.len(), sig.len()) {
                     if arg_slice[x].is_err() {
                         continue;
                     }
-                    if !sig.0[x].contains(&arg_slice[x].as_ref().unwrap().ttype) {
-                        arg_slice[x] = Err(Error::from_token(
-                            arg_slice[x].clone().unwrap(),
-                            ErrorType::ArgTypeMismatch,
-                        ))
+                    let t = arg_slice[x].as_ref().unwrap();
+                    if sig.0[x].contains(&TokenType::Comparison)
+                        && t.content.parse::<Comp>().is_ok()
+                    {
+                        // a valid operator only lexes as something else when it's disabled
+                        if t.ttype != TokenType::Comparison {
+                            arg_slice[x] =
+                                Err(Error::from_token(t.clone(), ErrorType::DisabledComparison));
+                        }
+                    } else if !sig.0[x].contains(&t.ttype) {
+                        arg_slice[x] = Err(Error::from_token(t.clone(), ErrorType::ArgTypeMismatch))
                     }
                 }
             }
             if line.len() - 1 != sig.len() {
-                let row = line.row();
                 let col = line.last_col();
                 let len = line.len() - 1;
-                line.push(Err(Error::new(
-                    row,
+                let err = line.error(
                     col,
                     format!("Expected {} args, found {}", sig.len(), len),
                     ErrorType::SigLenMismatch,
-                )));
+                );
+                line.push(Err(err));
             }
         }
     }
 
-    fn expand_macros(&self, raw: Vec<Line>) -> Vec<Line> {
-        let mut expanded = Vec::new();
-        let mut in_macro = false;
-        let mut start = 0;
-        for (x, line) in raw.iter().enumerate() {
+    /// removes `@const NAME value` declarations, and substitutes their values
+    /// into every argument that accepts a `Number` or a `Keyword`
+    ///
+    /// the constants are returned as well, for use in macro expressions
+    fn substitute_constants(&self, lines: Vec<Line>) -> (Vec<Line>, HashMap<String, Token>) {
+        let mut constants: HashMap<String, Token> = HashMap::new();
+        let mut rest = Vec::with_capacity(lines.len());
+        for line in lines {
+            match line[0].as_ref() {
+                Ok(t) if t.ttype == TokenType::ConstDef => (),
+                _ => {
+                    rest.push(line);
+                    continue;
+                }
+            }
+            if line.has_error() {
+                rest.push(line);
+                continue;
+            }
+            if line.len() != 3 {
+                let err = line.error(
+                    line.last_col(),
+                    format!("Expected 2 args, found {}", line.len() - 1),
+                    ErrorType::SigLenMismatch,
+                );
+                rest.push(err.into());
+                continue;
+            }
+            let name = line[1].clone().unwrap();
+            let value = line[2].clone().unwrap();
+            if name.ttype != TokenType::JumpLabel {
+                rest.push(Error::from_token(name, ErrorType::InvalidConstant).into());
+                continue;
+            }
+            if value.ttype != TokenType::Number && value.ttype != TokenType::Keyword {
+                rest.push(Error::from_token(value, ErrorType::InvalidConstant).into());
+                continue;
+            }
+            if constants.contains_key(&name.content) {
+                rest.push(Error::from_token(name, ErrorType::DuplicateConstant).into());
+                continue;
+            }
+            constants.insert(name.content, value);
+        }
+        for line in rest.iter_mut() {
+            self.substitute_line_constants(line, &constants);
+        }
+        (rest, constants)
+    }
+
+    fn substitute_line_constants(&self, line: &mut Line, constants: &HashMap<String, Token>) {
+        let first = match line[0].as_ref() {
+            Ok(t) => t.clone(),
+            Err(_) => return,
+        };
+        let slots = match first.ttype {
+            TokenType::MacroStart => vec![vec![TokenType::Number]],
+            TokenType::OpCode => match OpCode::try_from(first)
+                .ok()
+                .and_then(|op| self.instruction_signatures.get(&op))
+            {
+                Some(sig) => sig.0.clone(),
+                None => return,
+            },
+            _ => return,
+        };
+        for (x, slot) in slots.iter().enumerate() {
+            let t = match line.get(x + 1) {
+                Some(Ok(t)) => t.clone(),
+                _ => continue,
+            };
+            if t.ttype != TokenType::JumpLabel
+                || slot.contains(&TokenType::JumpLabel)
+                || !(slot.contains(&TokenType::Number) || slot.contains(&TokenType::Keyword))
+            {
+                continue;
+            }
+            line[x + 1] = match constants.get(&t.content) {
+                Some(value) => Ok(Token {
+                    content: value.content.clone(),
+                    ttype: value.ttype,
+                    ..t
+                }),
+                None => Err(Error::from_token(t, ErrorType::UndefinedConstant)),
+            };
+        }
+    }
+
+    fn expand_macros(&self, raw: Vec<Line>, constants: &HashMap<String, Token>) -> Vec<Line> {
+        let mut expanded = Vec::with_capacity(raw.len());
+        let mut state = MacroState {
+            counters: Vec::new(),
+            blocks: 0,
+            constants,
+        };
+        self.expand_block(&raw, &mut state, &mut expanded);
+        expanded
+    }
+
+    /// expands every `@rep` block in `lines` into `expanded`
+    fn expand_block(&self, lines: &[Line], state: &mut MacroState, expanded: &mut Vec<Line>) {
+        let mut x = 0;
+        while x < lines.len() {
+            let line = &lines[x];
             let first = match line[0].as_ref() {
                 Ok(t) => t,
-                Err(_) => continue,
+                Err(_) => {
+                    expanded.push(line.to_owned());
+                    x += 1;
+                    continue;
+                }
             };
             match first.ttype {
-                TokenType::MacroStart => {
-                    if in_macro {
-                        expanded.push(
-                            Error::from_token(first.to_owned(), ErrorType::NestedMacros).into(),
-                        );
-                    } else {
-                        start = x;
-                        in_macro = true;
+                TokenType::MacroStart => match Self::find_macro_end(lines, x) {
+                    Some(end) => {
+                        self.repeat_macro(&lines[x..=end], state, expanded);
+                        x = end + 1;
                     }
-                }
-                TokenType::MacroEnd => {
-                    if in_macro {
-                        expanded.append(&mut self.repeat_macro(&raw[start..=x]));
-                        in_macro = false;
-                    } else {
+                    None => {
                         expanded.push(
-                            Error::from_token(first.to_owned(), ErrorType::MissingRepTag).into(),
+                            Error::from_token(first.to_owned(), ErrorType::MissingEndTag).into(),
                         );
+                        return;
                     }
+                },
+                TokenType::MacroEnd => {
+                    expanded
+                        .push(Error::from_token(first.to_owned(), ErrorType::MissingRepTag).into());
+                    x += 1;
                 }
                 _ => {
-                    if !in_macro {
-                        expanded.push(line.to_owned());
-                    }
+                    expanded.push(self.substitute_macro(line, state));
+                    x += 1;
                 }
             }
         }
-        if in_macro {
-            expanded.push(Error::new(start, 0, "", ErrorType::MissingEndTag).into());
+    }
+
+    /// finds the `@end` matching the `@rep` at `start`, skipping over nested blocks
+    fn find_macro_end(lines: &[Line], start: usize) -> Option<usize> {
+        let mut depth = 0;
+        for (x, line) in lines.iter().enumerate().skip(start) {
+            match line[0].as_ref().map(|t| t.ttype) {
+                Ok(TokenType::MacroStart) => depth += 1,
+                Ok(TokenType::MacroEnd) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(x);
+                    }
+                }
+                _ => (),
+            }
         }
-        expanded
+        None
     }
 
-    fn repeat_macro(&self, lines: &[Line]) -> Vec<Line> {
-        let rep_count = match Self::get_rep_count(&lines[0]) {
+    fn repeat_macro(&self, lines: &[Line], state: &mut MacroState, expanded: &mut Vec<Line>) {
+        // the rep count itself may use the counters of enclosing blocks
+        let header = self.substitute_macro(&lines[0], state);
+        let rep_count = match Self::get_rep_count(&header) {
             Ok(r) => r,
-            Err(e) => return vec![e.into()],
+            Err(e) => {
+                // a count that was substituted, like `@{N - 1}`, is reported as written
+                let e = match (header.get(1), lines[0].get(1)) {
+                    (Some(Ok(_)), Some(Ok(t))) => Error::from_token(t.clone(), e.etype),
+                    _ => e,
+                };
+                expanded.push(e.into());
+                return;
+            }
         };
-        let mut expanded = Vec::with_capacity((lines.len() - 2) * rep_count);
+        let block = state.blocks;
+        state.blocks += 1;
         for x in 0..rep_count {
-            for line in &lines[1..(lines.len() - 1)] {
-                expanded.push(self.substitute_macro(line, x as i16));
+            let mut iteration = Vec::new();
+            state.counters.push(x as i16);
+            self.expand_block(&lines[1..(lines.len() - 1)], state, &mut iteration);
+            state.counters.pop();
+            for line in iteration.iter_mut() {
+                Self::scope_local_label(line, block, x, rep_count);
             }
+            expanded.append(&mut iteration);
         }
-        expanded
     }
 
-    fn substitute_macro(&self, line: &Line, x: i16) -> Line {
+    /// makes a local label (one starting with `.`) unique to an iteration of its innermost `@rep`
+    ///
+    /// `.NAME` refers to the label in the same iteration,
+    /// `.NAME-` to the previous one, and `.NAME+` to the next one.
+    /// references wrap around, so `.NAME+` in the last iteration is the label of the first
+    fn scope_local_label(line: &mut Line, block: usize, iteration: usize, rep_count: usize) {
+        let is_label_op = match line.first() {
+            Some(Ok(t)) => {
+                ["mark", "jump", "tjmp", "fjmp", "repl"].contains(&&t.content.to_lowercase()[..])
+            }
+            _ => false,
+        };
+        let label = match line.get_mut(1) {
+            Some(Ok(t)) if is_label_op && t.ttype == TokenType::JumpLabel => t,
+            _ => return,
+        };
+        let name = match label.content.strip_prefix('.') {
+            Some(n) if !n.is_empty() => n,
+            _ => return,
+        };
+        let (name, target) = match (name.strip_suffix('+'), name.strip_suffix('-')) {
+            (Some(n), _) => (n, (iteration + 1) % rep_count),
+            (_, Some(n)) => (n, (iteration + rep_count - 1) % rep_count),
+            _ => (name, iteration),
+        };
+        label.content = format!("{}@{}.{}", name, block, target);
+    }
+
+    fn substitute_macro(&self, line: &Line, state: &MacroState) -> Line {
         let mut new_line = Line::with_capacity(line.len());
         for res in line.iter() {
             match res {
                 Ok(t) => {
                     if t.ttype == TokenType::MacroReplace {
-                        let mut content = t.content.clone();
-                        while let Some(r_match) = self.macro_regex.find(&content) {
-                            let (range, base, inc) = Self::get_substitution_params(r_match);
-                            let num = base + (x * inc);
-                            content.replace_range(range, &num.to_string());
-                        }
-                        new_line.push(self.infer_arg_type(t.row, t.col, &content));
+                        new_line.push(self.substitute_token(t, state));
                     } else {
                         new_line.push(Ok(t.clone()));
                     }
@@ -432,35 +912,61 @@ impl Compiler {
         new_line
     }
 
-    fn get_substitution_params(r_match: Match) -> (Range<usize>, i16, i16) {
-        let range = r_match.range();
-        let mut split = r_match.as_str().split(',');
-        let base = split
-            .next()
-            .unwrap()
-            .trim_start_matches("@{")
-            .parse::<i16>()
-            .unwrap();
-        let inc = split
-            .next()
-            .unwrap()
-            .trim_end_matches('}')
-            .parse::<i16>()
-            .unwrap();
-        (range, base, inc)
+    /// replaces every `@{...}` in `t` with the value of its expression
+    ///
+    /// a keyword value keeps its delimiters if it makes up the whole token
+    fn substitute_token(&self, t: &Token, state: &MacroState) -> Result<Token, Error> {
+        let scope = expression::Scope {
+            counters: &state.counters,
+            constants: state.constants,
+            keyword_delimiter: self.keyword_delimiter,
+        };
+        let mut content = String::with_capacity(t.content.len());
+        let mut last = 0;
+        for r_match in self.macro_regex.find_iter(&t.content) {
+            let expr = &r_match.as_str()[2..r_match.len() - 1];
+            let error = |etype| Error {
+                col: t.col + t.content[..r_match.start()].chars().count(),
+                context: r_match.as_str().to_string(),
+                ..Error::from_token(t.clone(), etype)
+            };
+            let text = match expression::evaluate(expr, &scope).map_err(error)? {
+                Value::Number(n) if !REGISTER_RANGE.contains(&n) => {
+                    return Err(error(ErrorType::NumberOutOfRange))
+                }
+                Value::Number(n) => n.to_string(),
+                Value::Keyword(k) if r_match.range() == (0..t.content.len()) => {
+                    escape_keyword(&k, self.keyword_delimiter)
+                }
+                Value::Keyword(k) => k,
+            };
+            content.push_str(&t.content[last..r_match.start()]);
+            content.push_str(&text);
+            last = r_match.end();
+        }
+        content.push_str(&t.content[last..]);
+        let ttype = self.infer_arg_type(t.row, t.col, &content)?.ttype;
+        Ok(Token {
+            content,
+            ttype,
+            ..t.clone()
+        })
     }
 
     fn get_rep_count(line: &Line) -> Result<usize, Error> {
         let arg = match line.get(1) {
             Some(r) => r,
             None => {
-                return Err(Error::new(line.row(), 5, "", ErrorType::InvalidNumber));
+                return Err(line.error(line.last_col() + 1, "", ErrorType::InvalidNumber));
             }
         };
         match arg {
             Ok(t) => {
                 if t.ttype == TokenType::Number {
-                    Ok(t.content.parse().unwrap())
+                    // negative counts, written out or substituted, can't be repeated
+                    t.content
+                        .parse()
+                        .map_err(|_| Error::from_token(t.clone(), ErrorType::NumberOutOfRange))
                 } else {
                     Err(Error::from_token(t.clone(), ErrorType::InvalidNumber))
                 }
@@ -469,57 +975,358 @@ impl Compiler {
         }
     }
 
-    fn tokenize(&self, raw: &[&str]) -> Vec<Line> {
+    /// renders compiled instructions back into source, quoting keywords with the configured delimiter
+    ///
+    /// jumps are left as instruction indices, since labels don't survive compilation
+    pub fn disassemble(&self, instructions: &[Instruction]) -> Vec<String> {
+        instructions
+            .iter()
+            .map(|i| i.disassemble(self.keyword_delimiter))
+            .collect()
+    }
+
+    /// instructions accepted by this compiler, sorted by name
+    pub fn opcodes(&self) -> Vec<OpCode> {
+        let mut ops: Vec<OpCode> = self.instruction_signatures.keys().copied().collect();
+        ops.sort_by_key(|op| op.to_string());
+        ops
+    }
+
+    pub fn hardware_registers(&self) -> &[String] {
+        &self.hardware_registers
+    }
+
+    /// tokenizes every line on its own, without expanding macros or resolving directives
+    ///
+    /// meant for tooling, comments are kept in `Line::comment`, and blank lines are skipped
+    pub fn lex(&self, raw: &[&str]) -> Vec<Line> {
+        let mut tokenized = Vec::with_capacity(raw.len());
+        for (x, line) in raw.iter().enumerate() {
+            self.tokenize_line(x, line, &mut tokenized);
+        }
+        tokenized
+    }
+
+    fn tokenize(&self, file: Option<Rc<str>>, raw: &[&str]) -> Vec<Line> {
         let mut tokenized: Vec<Line> = Vec::with_capacity(raw.len());
+        let mut include_stack = Vec::new();
+        if let Some(path) = file
+            .as_ref()
+            .and_then(|f| Path::new(&**f).canonicalize().ok())
+        {
+            include_stack.push(path);
+        }
+        let mut sources = Vec::with_capacity(raw.len());
+        let lines: Vec<String> = raw.iter().map(|l| l.to_string()).collect();
+        self.resolve_includes(
+            file,
+            &lines,
+            &mut include_stack,
+            &mut sources,
+            &mut tokenized,
+        );
+        let (definitions, rest) = self.collect_definitions(sources, &mut tokenized);
+        for source in rest.iter() {
+            self.tokenize_source(source, &definitions, &mut Vec::new(), &mut tokenized);
+        }
+        tokenized
+    }
+
+    /// replaces every `@include "path"` line with the lines of the included file
+    ///
+    /// paths are resolved relative to the including file (or the working directory),
+    /// then against each of the configured `include_paths`
+    fn resolve_includes(
+        &self,
+        file: Option<Rc<str>>,
+        raw: &[String],
+        include_stack: &mut Vec<PathBuf>,
+        sources: &mut Vec<SourceLine>,
+        tokenized: &mut Vec<Line>,
+    ) {
         for (x, line) in raw.iter().enumerate() {
-            if line.is_empty() {
+            if !is_directive(line, "@include") {
+                sources.push(SourceLine {
+                    file: file.clone(),
+                    row: x,
+                    text: line.to_owned(),
+                });
                 continue;
             }
-
-            // skip comments
-            for c in self.comment_prefixes.iter() {
-                if line.to_lowercase().starts_with(c) {
-                    tokenized.push(Token::new(x, 0, line, TokenType::Comment).into());
+            let mut push_err = |col: usize, content: &str, etype: ErrorType| {
+                let mut err = Error::new(x, col, content, etype);
+                err.file = file.clone();
+                tokenized.push(err.into());
+            };
+            let indent = line.len() - line.trim_start().len();
+            let col = line[..indent].chars().count() + "@include ".len();
+            let target = line[indent + "@include".len()..].trim().trim_matches('"');
+            if target.is_empty() {
+                push_err(0, line, ErrorType::IncludeNotFound);
+                continue;
+            }
+            let path = match self.find_include(file.as_deref(), target) {
+                Some(p) => p,
+                None => {
+                    push_err(col, target, ErrorType::IncludeNotFound);
                     continue;
                 }
+            };
+            if include_stack.contains(&path) {
+                push_err(col, target, ErrorType::IncludeCycle);
+                continue;
             }
+            let content = match std::fs::read_to_string(&path) {
+                Ok(c) => c,
+                Err(_) => {
+                    push_err(col, target, ErrorType::IncludeNotFound);
+                    continue;
+                }
+            };
+            let included: Vec<String> = source_lines(&content)
+                .into_iter()
+                .map(|l| l.to_string())
+                .collect();
+            include_stack.push(path.clone());
+            self.resolve_includes(
+                Some(path.display().to_string().into()),
+                &included,
+                include_stack,
+                sources,
+                tokenized,
+            );
+            include_stack.pop();
+        }
+    }
+
+    fn find_include(&self, including: Option<&str>, target: &str) -> Option<PathBuf> {
+        let base = match including.and_then(|f| Path::new(f).parent()) {
+            Some(dir) => dir.to_path_buf(),
+            None => PathBuf::new(),
+        };
+        std::iter::once(base)
+            .chain(self.include_paths.iter().map(PathBuf::from))
+            .map(|dir| dir.join(target))
+            .find(|p| p.is_file())
+            .and_then(|p| p.canonicalize().ok())
+    }
 
-            // filter out short lines
-            if line.len() < 4 {
-                tokenized.push(Error::new(x, 0, line, ErrorType::UnknownInstruction).into());
+    /// splits `@def ... @end` blocks out of `sources`, returning them with the remaining lines
+    fn collect_definitions(
+        &self,
+        sources: Vec<SourceLine>,
+        tokenized: &mut Vec<Line>,
+    ) -> (HashMap<String, Definition>, Vec<SourceLine>) {
+        let mut definitions = HashMap::new();
+        let mut rest = Vec::with_capacity(sources.len());
+        let mut push_err = |source: &SourceLine, col: usize, content: &str, etype: ErrorType| {
+            let mut err = Error::new(source.row, col, content, etype);
+            err.file = source.file.clone();
+            tokenized.push(err.into());
+        };
+        let mut sources = sources.into_iter();
+        while let Some(header_line) = sources.next() {
+            if !is_directive(&header_line.text, "@def") {
+                rest.push(header_line);
                 continue;
             }
+            let header = self.slice_line(&header_line.text);
+            let mut body = Vec::new();
+            let mut depth = 0;
+            let mut terminated = false;
+            for source in sources.by_ref() {
+                if is_directive(&source.text, "@end") {
+                    if depth == 0 {
+                        terminated = true;
+                        break;
+                    }
+                    depth -= 1;
+                } else if is_directive(&source.text, "@rep") {
+                    depth += 1;
+                } else if is_directive(&source.text, "@def") {
+                    push_err(&source, 0, &source.text, ErrorType::NestedDefinition);
+                    continue;
+                }
+                body.push(source);
+            }
+            if !terminated {
+                push_err(&header_line, 0, &header[0].1, ErrorType::MissingEndTag);
+            }
+            let (col, name) = match header.get(1) {
+                Some(n) => n.clone(),
+                None => {
+                    push_err(
+                        &header_line,
+                        0,
+                        &header_line.text,
+                        ErrorType::MissingMacroName,
+                    );
+                    continue;
+                }
+            };
+            if definitions.contains_key(&name) {
+                push_err(&header_line, col, &name, ErrorType::DuplicateMacro);
+                continue;
+            }
+            let params = header[2..].iter().map(|(_, p)| p.clone()).collect();
+            definitions.insert(name, Definition { params, body });
+        }
+        (definitions, rest)
+    }
 
-            let mut line_vec = Vec::with_capacity(4);
-            let sliced = self.slice_line(line);
+    fn tokenize_source(
+        &self,
+        source: &SourceLine,
+        definitions: &HashMap<String, Definition>,
+        expansion: &mut Vec<CallSite>,
+        tokenized: &mut Vec<Line>,
+    ) {
+        if is_directive(&source.text, "@call") {
+            self.expand_call(source, definitions, expansion, tokenized);
+            return;
+        }
+        let first = tokenized.len();
+        self.tokenize_line(source.row, &source.text, tokenized);
+        for line in tokenized[first..].iter_mut() {
+            line.set_origin(&source.file, expansion);
+        }
+    }
 
-            // handle special tests
-            if sliced[0].1.to_lowercase() == "test"
-                && (sliced[1].1.to_lowercase() == "eof" || sliced[1].1.to_lowercase() == "mrd")
-            {
-                tokenized.push(
-                    Token::new(x, 0, format!("test {}", sliced[1].1), TokenType::OpCode).into(),
+    /// substitutes the arguments of a `@call` into the body of its definition
+    ///
+    /// parameters are referenced in the body as `$NAME`, and may appear in any token position
+    fn expand_call(
+        &self,
+        source: &SourceLine,
+        definitions: &HashMap<String, Definition>,
+        expansion: &mut Vec<CallSite>,
+        tokenized: &mut Vec<Line>,
+    ) {
+        let x = source.row;
+        let mut push_err = |err: Error, expansion: &[CallSite]| {
+            let mut line: Line = err.into();
+            line.set_origin(&source.file, expansion);
+            tokenized.push(line);
+        };
+        let sliced = self.slice_line(&source.text);
+        let (col, name) = match sliced.get(1) {
+            Some(n) => n.clone(),
+            None => {
+                push_err(
+                    Error::new(x, 0, &source.text, ErrorType::MissingMacroName),
+                    expansion,
                 );
-                continue;
+                return;
             }
+        };
+        let def = match definitions.get(&name) {
+            Some(d) => d,
+            None => {
+                push_err(
+                    Error::new(x, col, name, ErrorType::UndefinedMacro),
+                    expansion,
+                );
+                return;
+            }
+        };
+        if expansion.iter().any(|c| c.name == name) {
+            push_err(
+                Error::new(x, col, name, ErrorType::RecursiveMacro),
+                expansion,
+            );
+            return;
+        }
+        let args = &sliced[2..];
+        if args.len() != def.params.len() {
+            push_err(
+                Error::new(
+                    x,
+                    col,
+                    format!("Expected {} args, found {}", def.params.len(), args.len()),
+                    ErrorType::MacroArgMismatch,
+                ),
+                expansion,
+            );
+            return;
+        }
 
-            let ttype = if sliced[0].1.to_lowercase() == "@rep" {
-                TokenType::MacroStart
-            } else if sliced[0].1.to_lowercase() == "@end" {
-                TokenType::MacroEnd
-            } else {
-                TokenType::OpCode
+        // longest names first, so `$AB` is not clobbered by `$A`
+        let mut substitutions: Vec<(String, &str)> = def
+            .params
+            .iter()
+            .zip(args.iter())
+            .map(|(p, (_, a))| (format!("${}", p), a.as_str()))
+            .collect();
+        substitutions.sort_by_key(|(p, _)| std::cmp::Reverse(p.len()));
+
+        expansion.push(CallSite {
+            file: source.file.clone(),
+            row: x,
+            col,
+            name,
+        });
+        for body_line in def.body.iter() {
+            let mut text = body_line.text.clone();
+            for (param, arg) in substitutions.iter() {
+                text = text.replace(param, arg);
+            }
+            let substituted = SourceLine {
+                text,
+                ..body_line.clone()
             };
+            self.tokenize_source(&substituted, definitions, expansion, tokenized);
+        }
+        expansion.pop();
+    }
 
-            line_vec.push(Ok(Token::new(x, 0, &sliced[0].1, ttype)));
+    fn tokenize_line(&self, x: usize, line: &str, tokenized: &mut Vec<Line>) {
+        let (sliced, comment) = self.lex_line(line);
+        let comment = comment.map(|(col, c)| Token::new(x, col, c, TokenType::Comment));
+
+        // blank or comment-only line
+        if sliced.is_empty() {
+            if let Some(c) = comment {
+                tokenized.push(Line::comment(c));
+            }
+            return;
+        }
 
-            for (col, arg) in &sliced[1..] {
-                line_vec.push(self.infer_arg_type(x, *col, arg));
+        let mut line_vec = Vec::with_capacity(4);
+        let (op_col, op) = &sliced[0];
+
+        // handle special tests
+        if op.to_lowercase() == "test" && sliced.len() == 2 {
+            let arg = sliced[1].1.to_lowercase();
+            if arg == "eof" || arg == "mrd" {
+                let mut line: Line = Token::new(
+                    x,
+                    *op_col,
+                    format!("test {}", sliced[1].1),
+                    TokenType::OpCode,
+                )
+                .into();
+                line.comment = comment;
+                tokenized.push(line);
+                return;
             }
+        }
+
+        let ttype = match &op.to_lowercase()[..] {
+            "@rep" => TokenType::MacroStart,
+            "@end" => TokenType::MacroEnd,
+            "@const" => TokenType::ConstDef,
+            _ => TokenType::OpCode,
+        };
+
+        line_vec.push(Ok(Token::new(x, *op_col, op, ttype)));
 
-            tokenized.push(line_vec.into());
+        for (col, arg) in &sliced[1..] {
+            line_vec.push(self.infer_arg_type(x, *col, arg));
         }
-        tokenized
+
+        let mut line: Line = line_vec.into();
+        line.comment = comment;
+        tokenized.push(line);
     }
 
     fn infer_arg_type(&self, row: usize, col: usize, content: &String) -> Result<Token, Error> {
@@ -552,12 +1359,14 @@ impl Compiler {
 
         #[inline(always)]
         fn is_reg_label(arg: &str) -> bool {
-            (arg.len() == 1 && "XTFM".contains(&arg.to_uppercase())) || arg.starts_with('#')
+            (arg.chars().count() == 1 && "XTFM".contains(&arg.to_uppercase()))
+                || arg.starts_with('#')
         }
 
         #[inline(always)]
         fn is_keyword(delim: char, arg: &str) -> bool {
-            arg.starts_with(delim) && arg.ends_with(delim)
+            // unterminated literals are reported once the keyword is parsed
+            arg.starts_with(delim)
         }
 
         #[inline(always)]
@@ -566,38 +1375,198 @@ impl Compiler {
         }
     }
 
-    fn slice_line(&self, line: &str) -> Vec<(usize, String)> {
-        let mut sliced = vec![(0, line[..4].to_string())];
+    fn slice_line(&self, line: &str) -> Vec<Word> {
+        self.lex_line(line).0
+    }
 
-        if line.len() > 5 {
-            let arg_slice = &line[5..];
-            let mut start: usize = 0;
-            let mut mid_word: bool = false;
-            let mut x = 0;
-            while x < arg_slice.len() {
-                let curr_char = arg_slice.chars().nth(x).unwrap();
-                if curr_char == self.keyword_delimiter {
-                    mid_word = !mid_word;
-                    if !mid_word {
-                        sliced.push((start + 5, arg_slice[start..(x + 1)].to_string()));
+    /// splits a line into `(column, word)` pairs, and an optional trailing comment
+    ///
+    /// columns are counted in characters, words are separated by any amount of whitespace,
+    /// and keyword literals may contain whitespace
+    fn lex_line(&self, line: &str) -> (Vec<Word>, Option<Word>) {
+        let chars: Vec<char> = line.chars().collect();
+        let mut words = Vec::new();
+        let mut x = 0;
+        loop {
+            while x < chars.len() && chars[x].is_whitespace() {
+                x += 1;
+            }
+            if x >= chars.len() {
+                return (words, None);
+            }
+            if self.is_comment_start(&chars[x..], words.is_empty()) {
+                return (words, Some((x, chars[x..].iter().collect())));
+            }
+            let start = x;
+            if chars[x] == self.keyword_delimiter {
+                x = self.keyword_end(&chars, x);
+            } else {
+                while x < chars.len() && !chars[x].is_whitespace() {
+                    if chars[x] == '@' && chars.get(x + 1) == Some(&'{') {
+                        // macro expressions may contain spaces, keywords included
+                        x = self.macro_end(&chars, x);
+                    } else {
                         x += 1;
-                        start = x + 1;
                     }
                 }
-                if curr_char == ' ' && !mid_word {
-                    sliced.push((start + 5, arg_slice[start..x].to_string()));
-                    start = x + 1;
-                }
+            }
+            words.push((start, chars[start..x].iter().collect()));
+        }
+    }
+
+    /// index after the `}` closing the macro substitution starting at `start`,
+    /// or the end of the line if it's unclosed
+    fn macro_end(&self, chars: &[char], start: usize) -> usize {
+        let mut x = start + 2;
+        while x < chars.len() {
+            if chars[x] == self.keyword_delimiter {
+                x = self.keyword_end(chars, x);
+            } else if chars[x] == '}' {
+                return x + 1;
+            } else {
                 x += 1;
             }
-            if arg_slice.len() > start {
-                sliced.push((start + 5, arg_slice[start..].to_string()));
+        }
+        chars.len()
+    }
+
+    /// index after the delimiter closing the keyword literal starting at `start`,
+    /// skipping over escaped characters, or the end of the line if it's unclosed
+    fn keyword_end(&self, chars: &[char], start: usize) -> usize {
+        let mut x = start + 1;
+        while x < chars.len() {
+            match chars[x] {
+                '\\' => x += 2,
+                c if c == self.keyword_delimiter => return x + 1,
+                _ => x += 1,
             }
         }
-        sliced
+        chars.len()
+    }
+
+    /// checks whether a comment starts at the beginning of `rest`
+    ///
+    /// `comment_prefixes` start a comment at the beginning of a line,
+    /// `inline_comment_prefixes` can also follow an instruction.
+    /// an inline prefix that could start a token (like `#` in `#NERV`) must be followed by whitespace
+    fn is_comment_start(&self, rest: &[char], line_start: bool) -> bool {
+        let prefixes = match line_start {
+            true => &self.comment_prefixes,
+            false => &self.inline_comment_prefixes,
+        };
+        prefixes.iter().any(|prefix| {
+            let prefix: Vec<char> = prefix.chars().collect();
+            if prefix.is_empty()
+                || rest.len() < prefix.len()
+                || !rest
+                    .iter()
+                    .zip(prefix.iter())
+                    .all(|(a, b)| a.to_lowercase().eq(b.to_lowercase()))
+            {
+                return false;
+            }
+            if line_start || !(prefix[0].is_alphanumeric() || prefix[0] == '#') {
+                return true;
+            }
+            rest.get(prefix.len()).is_none_or(|c| c.is_whitespace())
+        })
     }
 }
 
+/// a piece of a line, along with the column (in characters) it starts at
+type Word = (usize, String);
+
+/// reads a keyword literal, resolving the `\\`, `\s` (space) and delimiter escapes
+fn parse_keyword(literal: &str, delimiter: char) -> Result<String, ErrorType> {
+    let mut chars = literal.chars();
+    if chars.next() != Some(delimiter) {
+        return Err(ErrorType::UnterminatedKeyword);
+    }
+    let mut keyword = String::with_capacity(literal.len());
+    loop {
+        match chars.next() {
+            None => return Err(ErrorType::UnterminatedKeyword),
+            Some('\\') => match chars.next() {
+                Some('\\') => keyword.push('\\'),
+                Some('s') => keyword.push(' '),
+                Some(c) if c == delimiter => keyword.push(c),
+                _ => return Err(ErrorType::InvalidEscape),
+            },
+            Some(c) if c == delimiter => break,
+            Some(c) => keyword.push(c),
+        }
+    }
+    if chars.next().is_some() {
+        return Err(ErrorType::UnterminatedKeyword);
+    }
+    // the same limit `Register::from_str` enforces
+    if keyword.len() > 256 {
+        return Err(ErrorType::KeywordTooLong);
+    }
+    Ok(keyword)
+}
+
+/// splits a source text into lines, dropping a leading BOM and any CRs before line feeds
+fn source_lines(source: &str) -> Vec<&str> {
+    source.trim_start_matches('\u{feff}').lines().collect()
+}
+
+/// checks whether the first word of `line` is the directive `name`
+fn is_directive(line: &str, name: &str) -> bool {
+    match line.split_whitespace().next() {
+        Some(first) => first.to_lowercase() == name,
+        None => false,
+    }
+}
+
+/// a line of source text, along with the file it came from
+#[derive(Debug, Clone)]
+struct SourceLine {
+    file: Option<Rc<str>>,
+    row: usize,
+    text: String,
+}
+
+/// a named, parameterised block defined with `@def NAME PARAM...` and closed by `@end`
+#[derive(Debug, Clone)]
+struct Definition {
+    params: Vec<String>,
+    body: Vec<SourceLine>,
+}
+
+/// location of a `@call` that a token was expanded from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallSite {
+    pub file: Option<Rc<str>>,
+    pub row: usize,
+    pub col: usize,
+    pub name: String,
+}
+
+/// numbers a register can hold, macro results outside of it are `NumberOutOfRange`
+#[cfg(not(feature = "full-register-range"))]
+const REGISTER_RANGE: RangeInclusive<i32> = -9999..=9999;
+#[cfg(feature = "full-register-range")]
+const REGISTER_RANGE: RangeInclusive<i32> = i16::MIN as i32..=i16::MAX as i32;
+
+const METADATA_DIRECTIVES: [&str; 6] = [
+    "@name",
+    "@author",
+    "@description",
+    "@entry",
+    "@mode",
+    "@requires",
+];
+
+/// state threaded through `@rep` expansion
+struct MacroState<'a> {
+    /// iteration counter of each enclosing `@rep`, innermost last
+    counters: Vec<i16>,
+    /// number of `@rep` blocks expanded so far, to keep local labels unique
+    blocks: usize,
+    constants: &'a HashMap<String, Token>,
+}
+
 impl Default for Compiler {
     fn default() -> Self {
         Self::new(Config::default())
@@ -607,16 +1576,29 @@ impl Default for Compiler {
 #[derive(Debug, Clone)]
 pub struct Line {
     inner: Vec<Result<Token, Error>>,
+    /// comment at the end of the line, or making up the whole line if `inner` is empty
+    pub comment: Option<Token>,
 }
 
 impl Line {
     pub fn new() -> Self {
-        Self { inner: Vec::new() }
+        Self {
+            inner: Vec::new(),
+            comment: None,
+        }
     }
 
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
             inner: Vec::with_capacity(capacity),
+            comment: None,
+        }
+    }
+
+    pub fn comment(comment: Token) -> Self {
+        Self {
+            inner: Vec::new(),
+            comment: Some(comment),
         }
     }
 
@@ -630,16 +1612,83 @@ impl Line {
     }
 
     pub fn row(&self) -> usize {
+        match self.inner.first() {
+            Some(Ok(t)) => t.row,
+            Some(Err(e)) => e.row,
+            None => self.comment.as_ref().map_or(0, |c| c.row),
+        }
+    }
+
+    pub fn expansion(&self) -> &[CallSite] {
         match &self[0] {
-            Ok(t) => t.row,
-            Err(e) => e.row,
+            Ok(t) => &t.expansion,
+            Err(e) => &e.expansion,
+        }
+    }
+
+    pub fn location(&self) -> SourceLocation {
+        let row = self.row();
+        let col = match &self[0] {
+            Ok(t) => t.col,
+            Err(e) => e.col,
+        };
+        // substituted tokens can come from other rows
+        let end = self
+            .iter()
+            .flatten()
+            .filter(|t| t.row == row)
+            .map(|t| t.col + t.content.chars().count())
+            .max()
+            .unwrap_or(0);
+        SourceLocation {
+            file: self.file().clone(),
+            row,
+            col,
+            len: usize::max(end.saturating_sub(col), 1),
         }
     }
 
+    pub fn file(&self) -> &Option<Rc<str>> {
+        match &self[0] {
+            Ok(t) => &t.file,
+            Err(e) => &e.file,
+        }
+    }
+
+    pub fn set_origin(&mut self, file: &Option<Rc<str>>, expansion: &[CallSite]) {
+        if let Some(c) = self.comment.as_mut() {
+            c.file = file.clone();
+            c.expansion = expansion.to_vec();
+        }
+        for res in self.inner.iter_mut() {
+            match res {
+                Ok(t) => {
+                    t.file = file.clone();
+                    t.expansion = expansion.to_vec();
+                }
+                Err(e) => {
+                    e.file = file.clone();
+                    e.expansion = expansion.to_vec();
+                }
+            }
+        }
+    }
+
+    /// creates an error located on this line
+    pub fn error<T>(&self, col: usize, content: T, etype: ErrorType) -> Error
+    where
+        T: Display,
+    {
+        let mut err = Error::new(self.row(), col, content, etype);
+        err.file = self.file().clone();
+        err.expansion = self.expansion().to_vec();
+        err
+    }
+
     pub fn last_col(&self) -> usize {
         match self.inner.last().unwrap() {
-            Ok(t) => t.col + t.content.len(),
-            Err(e) => e.col + e.context.len(),
+            Ok(t) => t.col + t.content.chars().count(),
+            Err(e) => e.col + e.context.chars().count(),
         }
     }
 }
@@ -665,7 +1714,10 @@ impl Default for Line {
 
 impl From<Vec<Result<Token, Error>>> for Line {
     fn from(value: Vec<Result<Token, Error>>) -> Self {
-        Self { inner: value }
+        Self {
+            inner: value,
+            comment: None,
+        }
     }
 }
 
@@ -673,6 +1725,7 @@ impl From<Token> for Line {
     fn from(value: Token) -> Self {
         Self {
             inner: vec![Ok(value)],
+            comment: None,
         }
     }
 }
@@ -681,6 +1734,7 @@ impl From<Error> for Line {
     fn from(value: Error) -> Self {
         Self {
             inner: vec![Err(value)],
+            comment: None,
         }
     }
 }
@@ -0,0 +1,304 @@
+use std::rc::Rc;
+
+use crate::compiler::cfg::{self, Cfg};
+use crate::compiler::Program;
+use crate::exa::{Arg, Instruction, OpCode, RegLabel};
+
+/// how much a warning matters, independent of `ErrorType` since warnings never block compilation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Hint,
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningType {
+    /// no path from the start of the program (or a `REPL` target) reaches the instruction
+    UnreachableCode,
+    /// execution can run past the last instruction, which errors with `OutOfInstructions`
+    FallsOffEnd,
+    /// `F` is used on a path where no file was `GRAB`bed or `MAKE`d
+    NoFileHeld,
+    /// a `MARK` that is never jumped to
+    UnusedLabel,
+    /// `DIVI` or `MODI` by a literal 0
+    DivisionByZero,
+    /// arithmetic on `X` or `T` while it always holds a keyword, errors with `NumericValueRequired`
+    KeywordArithmetic,
+    /// `TEST` between a number and a keyword, which is always false (or always true for `!=`)
+    MismatchedComparison,
+}
+
+impl WarningType {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::UnusedLabel => Severity::Hint,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Warning {
+    pub file: Option<Rc<str>>,
+    pub row: usize,
+    pub col: usize,
+    /// characters the warning spans on `row`
+    pub len: usize,
+    pub context: String,
+    pub wtype: WarningType,
+    pub severity: Severity,
+}
+
+impl Warning {
+    fn at_instruction(program: &Program, index: usize, wtype: WarningType) -> Self {
+        let location = &program.source_map[index];
+        Self {
+            file: location.file.clone(),
+            row: location.row,
+            col: location.col,
+            len: location.len,
+            context: program.instructions[index].to_string(),
+            wtype,
+            severity: wtype.severity(),
+        }
+    }
+}
+
+/// runs every lint over a compiled program
+pub fn analyze(program: &Program) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    if program.entry() >= program.len() {
+        return warnings;
+    }
+    let cfg = Cfg::new(program);
+    let reachable = reachability(program, &cfg);
+    unreachable_code(program, &reachable, &mut warnings);
+    falls_off_end(program, &cfg, &mut warnings);
+    file_not_held(program, &reachable, &mut warnings);
+    unused_labels(program, &mut warnings);
+    division_by_zero(program, &mut warnings);
+    type_mismatches(program, &reachable, &mut warnings);
+    warnings.sort_by_key(|w| (w.file.clone(), w.row, w.col));
+    warnings
+}
+
+/// expands block reachability to every instruction
+fn reachability(program: &Program, cfg: &Cfg) -> Vec<bool> {
+    let blocks = cfg.reachable();
+    (0..program.len())
+        .map(|x| blocks[cfg.block_of(x)])
+        .collect()
+}
+
+fn unreachable_code(program: &Program, reachable: &[bool], warnings: &mut Vec<Warning>) {
+    // only report the start of each unreachable run
+    for x in 0..program.len() {
+        if !reachable[x] && (x == 0 || reachable[x - 1]) {
+            warnings.push(Warning::at_instruction(
+                program,
+                x,
+                WarningType::UnreachableCode,
+            ));
+        }
+    }
+}
+
+fn falls_off_end(program: &Program, cfg: &Cfg, warnings: &mut Vec<Warning>) {
+    let reachable = cfg.reachable();
+    for (b, block) in cfg.blocks.iter().enumerate() {
+        if reachable[b] && block.falls_off_end {
+            warnings.push(Warning::at_instruction(
+                program,
+                block.end - 1,
+                WarningType::FallsOffEnd,
+            ));
+        }
+    }
+}
+
+/// forward dataflow over "a file might not be held here"
+fn file_not_held(program: &Program, reachable: &[bool], warnings: &mut Vec<Warning>) {
+    // `None` until the instruction is first visited
+    let mut may_lack_file: Vec<Option<bool>> = vec![None; program.len()];
+    may_lack_file[program.entry()] = Some(true);
+    let mut worklist = vec![program.entry()];
+    while let Some(x) = worklist.pop() {
+        let lacks_after = match program.instructions[x].0 {
+            OpCode::Grab | OpCode::Make => false,
+            OpCode::Drop | OpCode::Wipe => true,
+            _ => may_lack_file[x].unwrap(),
+        };
+        for (s, _) in cfg::successors(program, x).0 {
+            let merged = Some(may_lack_file[s].unwrap_or(false) || lacks_after);
+            if may_lack_file[s] != merged {
+                may_lack_file[s] = merged;
+                worklist.push(s);
+            }
+        }
+    }
+    for x in 0..program.len() {
+        if reachable[x] && may_lack_file[x].unwrap_or(false) && uses_file(&program.instructions[x])
+        {
+            warnings.push(Warning::at_instruction(program, x, WarningType::NoFileHeld));
+        }
+    }
+}
+
+fn uses_file(instr: &Instruction) -> bool {
+    match instr.0 {
+        OpCode::Seek | OpCode::File | OpCode::TestEof | OpCode::Drop | OpCode::Wipe => true,
+        _ => {
+            let (a, b, c) = instr.arg_refs();
+            [a, b, c]
+                .into_iter()
+                .flatten()
+                .any(|arg| matches!(arg, Arg::RegLabel(RegLabel::F)))
+        }
+    }
+}
+
+fn unused_labels(program: &Program, warnings: &mut Vec<Warning>) {
+    for label in program.labels.iter().filter(|l| l.uses == 0) {
+        warnings.push(Warning {
+            file: label.location.file.clone(),
+            row: label.location.row,
+            col: label.location.col,
+            len: label.location.len,
+            context: label.name.clone(),
+            wtype: WarningType::UnusedLabel,
+            severity: WarningType::UnusedLabel.severity(),
+        });
+    }
+}
+
+fn division_by_zero(program: &Program, warnings: &mut Vec<Warning>) {
+    for (x, instr) in program.instructions.iter().enumerate() {
+        if matches!(instr.0, OpCode::Divi | OpCode::Modi) && instr.2 == Some(Arg::Number(0)) {
+            warnings.push(Warning::at_instruction(
+                program,
+                x,
+                WarningType::DivisionByZero,
+            ));
+        }
+    }
+}
+
+/// what `X` or `T` is known to hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueType {
+    Number,
+    Keyword,
+    Unknown,
+}
+
+impl ValueType {
+    fn merge(self, other: Self) -> Self {
+        match self == other {
+            true => self,
+            false => Self::Unknown,
+        }
+    }
+}
+
+/// types of `X` and `T` before an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RegTypes {
+    x: ValueType,
+    t: ValueType,
+}
+
+impl RegTypes {
+    fn of(&self, arg: &Arg) -> ValueType {
+        match arg {
+            Arg::Number(_) => ValueType::Number,
+            Arg::Keyword(_) => ValueType::Keyword,
+            Arg::RegLabel(RegLabel::X) => self.x,
+            Arg::RegLabel(RegLabel::T) => self.t,
+            _ => ValueType::Unknown,
+        }
+    }
+
+    fn set(&mut self, target: &Option<Arg>, ty: ValueType) {
+        match target {
+            Some(Arg::RegLabel(RegLabel::X)) => self.x = ty,
+            Some(Arg::RegLabel(RegLabel::T)) => self.t = ty,
+            _ => (),
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Self {
+            x: self.x.merge(other.x),
+            t: self.t.merge(other.t),
+        }
+    }
+
+    fn after(mut self, instr: &Instruction) -> Self {
+        match instr.0 {
+            OpCode::Copy => self.set(&instr.2, self.of(instr.1.as_ref().unwrap())),
+            OpCode::Addi
+            | OpCode::Subi
+            | OpCode::Muli
+            | OpCode::Divi
+            | OpCode::Modi
+            | OpCode::Swiz
+            | OpCode::Rand => self.set(&instr.3, ValueType::Number),
+            OpCode::Void | OpCode::File => self.set(&instr.1, ValueType::Number),
+            OpCode::Host => self.set(&instr.1, ValueType::Keyword),
+            OpCode::Test | OpCode::TestEof | OpCode::TestMrd => self.t = ValueType::Number,
+            _ => (),
+        }
+        self
+    }
+}
+
+/// forward dataflow over the types `X` and `T` hold, both start out as the number 0
+fn type_mismatches(program: &Program, reachable: &[bool], warnings: &mut Vec<Warning>) {
+    // `None` until the instruction is first visited
+    let mut types: Vec<Option<RegTypes>> = vec![None; program.len()];
+    types[program.entry()] = Some(RegTypes {
+        x: ValueType::Number,
+        t: ValueType::Number,
+    });
+    let mut worklist = vec![program.entry()];
+    while let Some(x) = worklist.pop() {
+        let after = types[x].unwrap().after(&program.instructions[x]);
+        for (s, _) in cfg::successors(program, x).0 {
+            let merged = Some(types[s].map_or(after, |t| t.merge(after)));
+            if types[s] != merged {
+                types[s] = merged;
+                worklist.push(s);
+            }
+        }
+    }
+    for (x, instr) in program.instructions.iter().enumerate() {
+        let before = match types[x] {
+            Some(t) if reachable[x] => t,
+            _ => continue,
+        };
+        let wtype = match instr {
+            Instruction(
+                OpCode::Addi
+                | OpCode::Subi
+                | OpCode::Muli
+                | OpCode::Divi
+                | OpCode::Modi
+                | OpCode::Swiz
+                | OpCode::Rand,
+                Some(a),
+                Some(b),
+                _,
+            ) if before.of(a) == ValueType::Keyword || before.of(b) == ValueType::Keyword => {
+                WarningType::KeywordArithmetic
+            }
+            Instruction(OpCode::Test, Some(a), _, Some(b)) => match (before.of(a), before.of(b)) {
+                (ValueType::Number, ValueType::Keyword)
+                | (ValueType::Keyword, ValueType::Number) => WarningType::MismatchedComparison,
+                _ => continue,
+            },
+            _ => continue,
+        };
+        warnings.push(Warning::at_instruction(program, x, wtype));
+    }
+}
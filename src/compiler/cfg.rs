@@ -0,0 +1,272 @@
+use serde_json::{json, Value};
+
+use crate::compiler::Program;
+use crate::exa::{Instruction, OpCode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// execution continues with the next instruction
+    Fallthrough,
+    /// a `JUMP`, or a `TJMP`/`FJMP` whose condition held
+    Taken,
+    /// the entry point of an exa spawned by `REPL`
+    Repl,
+}
+
+impl EdgeKind {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Fallthrough => "fallthrough",
+            Self::Taken => "taken",
+            Self::Repl => "repl",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// a run of instructions only ever entered at its first one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// index of the first instruction
+    pub start: usize,
+    /// index one past the last instruction
+    pub end: usize,
+    /// names of the labels pointing at `start`
+    pub labels: Vec<String>,
+    /// execution can run past the last instruction of the program from this block
+    pub falls_off_end: bool,
+}
+
+/// control-flow graph of a compiled program
+///
+/// blocks end at `JUMP`, `TJMP`, `FJMP`, `REPL` and `HALT`,
+/// and start at the instruction after them or at a label
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg {
+    /// blocks in program order
+    pub blocks: Vec<BasicBlock>,
+    /// block execution starts in, `None` if the program is empty
+    /// or its `@entry` label is past the last instruction
+    pub entry: Option<usize>,
+    /// edges between blocks, by index into `blocks`
+    pub edges: Vec<Edge>,
+    /// block each instruction belongs to
+    block_of: Vec<usize>,
+}
+
+impl Cfg {
+    pub fn new(program: &Program) -> Self {
+        let len = program.len();
+        let mut leader = vec![false; len];
+        if len > 0 {
+            leader[0] = true;
+        }
+        for label in program.labels.iter().filter(|l| l.index < len) {
+            leader[label.index] = true;
+        }
+        for (x, instr) in program.instructions.iter().enumerate() {
+            if ends_block(instr) && x + 1 < len {
+                leader[x + 1] = true;
+            }
+            if let Some(t) = jump_target(instr).filter(|t| *t < len) {
+                leader[t] = true;
+            }
+        }
+
+        let mut blocks: Vec<BasicBlock> = Vec::new();
+        let mut block_of = Vec::with_capacity(len);
+        for (x, l) in leader.iter().enumerate() {
+            if *l {
+                blocks.push(BasicBlock {
+                    start: x,
+                    end: x,
+                    labels: program
+                        .labels
+                        .iter()
+                        .filter(|label| label.index == x)
+                        .map(|label| label.name.clone())
+                        .collect(),
+                    falls_off_end: false,
+                });
+            }
+            blocks.last_mut().unwrap().end = x + 1;
+            block_of.push(blocks.len() - 1);
+        }
+
+        let mut edges = Vec::new();
+        for (b, block) in blocks.iter_mut().enumerate() {
+            let last = block.end - 1;
+            let (succ, falls_off_end) = successors(program, last);
+            block.falls_off_end = falls_off_end;
+            for (to, kind) in succ {
+                edges.push(Edge {
+                    from: b,
+                    to: block_of[to],
+                    kind,
+                });
+            }
+        }
+
+        Self {
+            blocks,
+            edges,
+            entry: block_of.get(program.entry()).copied(),
+            block_of,
+        }
+    }
+
+    /// index of the block containing the instruction at `index`
+    pub fn block_of(&self, index: usize) -> usize {
+        self.block_of[index]
+    }
+
+    pub fn successors(&self, block: usize) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |e| e.from == block)
+    }
+
+    /// which blocks can be reached from the entry point
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut stack: Vec<usize> = self.entry.into_iter().collect();
+        while let Some(b) = stack.pop() {
+            if reachable[b] {
+                continue;
+            }
+            reachable[b] = true;
+            stack.extend(self.successors(b).map(|e| e.to));
+        }
+        reachable
+    }
+
+    /// renders the graph in Graphviz DOT, one node per block listing its instructions
+    pub fn to_dot(&self, program: &Program) -> String {
+        let mut out = String::from("digraph exa {\n    node [shape=box, fontname=monospace];\n");
+        for (b, block) in self.blocks.iter().enumerate() {
+            let mut label = String::new();
+            for name in block.labels.iter() {
+                label.push_str(&format!("MARK {}\\l", escape(name)));
+            }
+            for x in block.start..block.end {
+                label.push_str(&format!(
+                    "{}: {}\\l",
+                    x,
+                    escape(&program.instructions[x].to_string())
+                ));
+            }
+            out.push_str(&format!("    b{} [label=\"{}\"];\n", b, label));
+        }
+        if let Some(entry) = self.entry {
+            out.push_str(&format!(
+                "    start [shape=point];\n    start -> b{};\n",
+                entry
+            ));
+        }
+        for e in self.edges.iter() {
+            let style = match e.kind {
+                EdgeKind::Fallthrough => "solid",
+                EdgeKind::Taken => "bold",
+                EdgeKind::Repl => "dashed",
+            };
+            out.push_str(&format!(
+                "    b{} -> b{} [label=\"{}\", style={}];\n",
+                e.from,
+                e.to,
+                e.kind.name(),
+                style
+            ));
+        }
+        if self.blocks.iter().any(|b| b.falls_off_end) {
+            out.push_str("    end [shape=doublecircle, label=\"end\"];\n");
+            for (b, _) in self
+                .blocks
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| b.falls_off_end)
+            {
+                out.push_str(&format!("    b{} -> end [style=dotted];\n", b));
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// renders the graph as JSON, with the instructions and source rows of each block
+    pub fn to_json(&self, program: &Program) -> Value {
+        let blocks: Vec<Value> = self
+            .blocks
+            .iter()
+            .map(|b| {
+                let instructions: Vec<Value> = (b.start..b.end)
+                    .map(|x| {
+                        let location = &program.source_map[x];
+                        json!({
+                            "index": x,
+                            "instruction": program.instructions[x].to_string(),
+                            "file": location.file.as_deref(),
+                            "row": location.row,
+                        })
+                    })
+                    .collect();
+                json!({
+                    "start": b.start,
+                    "end": b.end,
+                    "labels": b.labels,
+                    "falls_off_end": b.falls_off_end,
+                    "instructions": instructions,
+                })
+            })
+            .collect();
+        let edges: Vec<Value> = self
+            .edges
+            .iter()
+            .map(|e| json!({ "from": e.from, "to": e.to, "kind": e.kind.name() }))
+            .collect();
+        json!({ "entry": self.entry, "blocks": blocks, "edges": edges })
+    }
+}
+
+/// instructions execution can continue at after `index`, and whether it can run off the end
+pub fn successors(program: &Program, index: usize) -> (Vec<(usize, EdgeKind)>, bool) {
+    let instr = &program.instructions[index];
+    let next = index + 1;
+    let mut succ = match (instr.0, jump_target(instr)) {
+        (OpCode::Halt, _) => return (vec![], false),
+        (OpCode::Jump, Some(t)) => vec![(t, EdgeKind::Taken)],
+        (OpCode::Tjmp | OpCode::Fjmp, Some(t)) => vec![(t, EdgeKind::Taken)],
+        (OpCode::Repl, Some(t)) => vec![(t, EdgeKind::Repl)],
+        _ => vec![],
+    };
+    if instr.0 != OpCode::Jump {
+        succ.push((next, EdgeKind::Fallthrough));
+    }
+    // the last instruction, or a label at the very end, leads past the end of the program
+    let falls_off_end = succ.iter().any(|(t, _)| *t >= program.len());
+    succ.retain(|(t, _)| *t < program.len());
+    (succ, falls_off_end)
+}
+
+fn ends_block(instr: &Instruction) -> bool {
+    matches!(
+        instr.0,
+        OpCode::Jump | OpCode::Tjmp | OpCode::Fjmp | OpCode::Repl | OpCode::Halt
+    )
+}
+
+fn jump_target(instr: &Instruction) -> Option<usize> {
+    match instr.0 {
+        OpCode::Jump | OpCode::Tjmp | OpCode::Fjmp | OpCode::Repl => {
+            instr.1.as_ref()?.jump_index().ok().map(|j| j as usize)
+        }
+        _ => None,
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -1,9 +1,12 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::compiler::{Signature, TokenType};
 
-use crate::exa::OpCode;
+use crate::exa::{Arg, Comp, Instruction, OpCode};
+
+/// names accepted by `Config::profile`
+pub const PROFILES: [&str; 3] = ["game-faithful", "extended", "sandboxed"];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -12,6 +15,37 @@ pub struct Config {
     pub full_comparisons: bool,
     pub keyword_delimiter: char,
     pub comment_prefixes: Vec<String>,
+    /// prefixes that start a comment after an instruction, like `ADDI X 1 X ;; bump`
+    #[serde(default)]
+    pub inline_comment_prefixes: Vec<String>,
+    /// hardware registers (like `#NERV`) available on the host, offered by editor tooling
+    #[serde(default)]
+    pub hardware_registers: Vec<String>,
+    /// directories searched for `@include`d files
+    #[serde(default)]
+    pub include_paths: Vec<String>,
+    /// run the peephole optimizer over compiled programs
+    #[serde(default)]
+    pub optimize: bool,
+    /// maximum number of instructions in a compiled program, capped at `u16::MAX`
+    #[serde(default = "default_max_program_size")]
+    pub max_program_size: usize,
+    /// opcodes allowed on top of the ones picked by `extra_instructions`, like `"prnt"`
+    #[serde(default)]
+    pub enabled_instructions: Vec<String>,
+    /// opcodes forbidden on this host, like `"kill"` or `"link"`, takes precedence over enabling
+    #[serde(default)]
+    pub disabled_instructions: Vec<String>,
+    /// comparison operators allowed on top of the ones picked by `full_comparisons`
+    #[serde(default)]
+    pub enabled_comparisons: Vec<String>,
+    /// comparison operators forbidden on this host, takes precedence over enabling
+    #[serde(default)]
+    pub disabled_comparisons: Vec<String>,
+}
+
+fn default_max_program_size() -> usize {
+    1024
 }
 
 impl Default for Config {
@@ -30,17 +64,46 @@ impl Default for Config {
 }
 
 impl Config {
+    /// looks up a named profile, see `PROFILES`
+    pub fn profile(name: &str) -> Option<Self> {
+        match name {
+            "game-faithful" => Some(Self::game_faithful()),
+            "extended" => Some(Self::extended()),
+            "sandboxed" => Some(Self::sandboxed()),
+            _ => None,
+        }
+    }
+
+    /// the instruction set and syntax of the game
+    pub fn game_faithful() -> Self {
+        Self::default()
+    }
+
+    /// the game's instruction set, without `KILL` and `LINK`, for running untrusted code
+    pub fn sandboxed() -> Self {
+        Self {
+            disabled_instructions: vec!["kill".to_string(), "link".to_string()],
+            ..Self::default()
+        }
+    }
+
     pub fn extended() -> Self {
-        Self::custom(
-            true,
-            true,
-            true,
-            '\'',
-            vec!["note", ";;", "//", "#"]
+        Self {
+            inline_comment_prefixes: vec![";;", "//", "#"]
                 .into_iter()
                 .map(|s| s.to_string())
                 .collect(),
-        )
+            ..Self::custom(
+                true,
+                true,
+                true,
+                '\'',
+                vec!["note", ";;", "//", "#"]
+                    .into_iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+            )
+        }
     }
 
     pub fn custom(
@@ -56,11 +119,32 @@ impl Config {
             full_comparisons,
             keyword_delimiter,
             comment_prefixes,
+            inline_comment_prefixes: Vec::new(),
+            hardware_registers: Vec::new(),
+            include_paths: Vec::new(),
+            optimize: false,
+            max_program_size: default_max_program_size(),
+            enabled_instructions: Vec::new(),
+            disabled_instructions: Vec::new(),
+            enabled_comparisons: Vec::new(),
+            disabled_comparisons: Vec::new(),
+        }
+    }
+
+    /// the opcodes and comparisons this configuration allows
+    pub fn instruction_set(&self) -> InstructionSet {
+        InstructionSet {
+            opcodes: self.generate_signatures().into_keys().collect(),
+            comparisons: self
+                .generate_comparisons()
+                .iter()
+                .filter_map(|c| c.parse().ok())
+                .collect(),
         }
     }
 
     pub fn generate_comparisons(&self) -> Vec<String> {
-        match self.full_comparisons {
+        let mut comparisons: Vec<String> = match self.full_comparisons {
             true => vec!["=", ">", "<", ">=", "<=", "!="]
                 .into_iter()
                 .map(|s| s.to_string())
@@ -69,12 +153,18 @@ impl Config {
                 .into_iter()
                 .map(|s| s.to_string())
                 .collect(),
+        };
+        for c in self.enabled_comparisons.iter() {
+            if c.parse::<Comp>().is_ok() && !comparisons.contains(c) {
+                comparisons.push(c.clone());
+            }
         }
+        comparisons.retain(|c| !self.disabled_comparisons.contains(c));
+        comparisons
     }
 
     pub fn generate_signatures(&self) -> HashMap<OpCode, Signature> {
         let r = vec![TokenType::RegisterLabel];
-        let rn = vec![TokenType::RegisterLabel, TokenType::Number];
         let vari = match self.keyword_literals {
             true => vec![
                 TokenType::RegisterLabel,
@@ -84,7 +174,6 @@ impl Config {
             false => vec![TokenType::RegisterLabel, TokenType::Number],
         };
         let c = vec![TokenType::Comparison];
-        let l = vec![TokenType::JumpLabel];
 
         let mut sigs: HashMap<OpCode, Signature> = HashMap::from_iter([
             (OpCode::Copy, Signature::two(&vari, &r)),
@@ -118,10 +207,37 @@ impl Config {
             (OpCode::Kill, Signature::empty()),
         ]);
 
-        if self.extra_instructions {
-            sigs.insert(OpCode::Prnt, Signature::one(&vari));
+        let extra = HashMap::from([(OpCode::Prnt, Signature::one(&vari))]);
+        for (op, sig) in extra {
+            if self.extra_instructions || parse_opcodes(&self.enabled_instructions).contains(&op) {
+                sigs.insert(op, sig);
+            }
+        }
+        for op in parse_opcodes(&self.disabled_instructions) {
+            sigs.remove(&op);
         }
 
         sigs
     }
 }
+
+fn parse_opcodes(names: &[String]) -> Vec<OpCode> {
+    names.iter().filter_map(|n| n.parse().ok()).collect()
+}
+
+/// the opcodes and comparison operators a host accepts, checked against exas arriving from elsewhere
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionSet {
+    pub opcodes: HashSet<OpCode>,
+    pub comparisons: HashSet<Comp>,
+}
+
+impl InstructionSet {
+    pub fn permits(&self, instr: &Instruction) -> bool {
+        let comparison_ok = match &instr.2 {
+            Some(Arg::Comp(c)) => self.comparisons.contains(c),
+            _ => true,
+        };
+        self.opcodes.contains(&instr.0) && comparison_ok
+    }
+}
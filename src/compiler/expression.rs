@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use crate::compiler::{parse_keyword, ErrorType, Token, TokenType};
+
+/// the result of a macro expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Number(i32),
+    Keyword(String),
+}
+
+/// what a macro expression can refer to
+pub struct Scope<'a> {
+    /// iteration counter of each enclosing `@rep`, innermost last
+    pub counters: &'a [i16],
+    pub constants: &'a HashMap<String, Token>,
+    pub keyword_delimiter: char,
+}
+
+/// evaluates the inside of a `@{...}` substitution
+///
+/// besides the original `base,inc` and `base,inc,level` forms, this accepts expressions of
+/// - numbers, and keywords quoted with the keyword delimiter
+/// - `I` for the counter of the innermost `@rep`, `I1`, `I2`... for the enclosing ones
+/// - names of `@const`s
+/// - `+`, `-`, `*`, `/`, `%` and parentheses, where `+` joins keywords with anything else
+pub fn evaluate(expr: &str, scope: &Scope) -> Result<Value, ErrorType> {
+    if expr.contains(',') {
+        return evaluate_legacy(expr, scope);
+    }
+    let mut parser = Parser {
+        chars: expr.chars().collect(),
+        pos: 0,
+        scope,
+    };
+    let value = parser.expr()?;
+    parser.skip_whitespace();
+    match parser.pos == parser.chars.len() {
+        true => Ok(value),
+        false => Err(ErrorType::InvalidExpression),
+    }
+}
+
+/// `base,inc` or `base,inc,level`, which is `base + inc * counter`
+fn evaluate_legacy(expr: &str, scope: &Scope) -> Result<Value, ErrorType> {
+    let parts: Vec<&str> = expr.split(',').collect();
+    if !(2..=3).contains(&parts.len()) {
+        return Err(ErrorType::InvalidExpression);
+    }
+    let parse = |s: &str| {
+        s.trim()
+            .parse::<i32>()
+            .map_err(|_| ErrorType::InvalidExpression)
+    };
+    let base = parse(parts[0])?;
+    let inc = parse(parts[1])?;
+    let level = match parts.get(2) {
+        Some(l) => l
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| ErrorType::InvalidExpression)?,
+        None => 0,
+    };
+    let counter = counter(scope, level)?;
+    inc.checked_mul(counter)
+        .and_then(|n| n.checked_add(base))
+        .map(Value::Number)
+        .ok_or(ErrorType::NumberOutOfRange)
+}
+
+fn counter(scope: &Scope, level: usize) -> Result<i32, ErrorType> {
+    match level < scope.counters.len() {
+        true => Ok(scope.counters[scope.counters.len() - 1 - level] as i32),
+        false => Err(ErrorType::MacroLevelOutOfRange),
+    }
+}
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    scope: &'a Scope<'a>,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    /// consumes `c` if it's the next non-whitespace character
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        match self.chars.get(self.pos) == Some(&c) {
+            true => {
+                self.pos += 1;
+                true
+            }
+            false => false,
+        }
+    }
+
+    fn expr(&mut self) -> Result<Value, ErrorType> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat('+') {
+                value = add(value, self.term()?)?;
+            } else if self.eat('-') {
+                value = arithmetic(value, self.term()?, i32::checked_sub)?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<Value, ErrorType> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat('*') {
+                value = arithmetic(value, self.unary()?, i32::checked_mul)?;
+            } else if self.eat('/') {
+                value = arithmetic(value, nonzero(self.unary()?)?, i32::checked_div)?;
+            } else if self.eat('%') {
+                value = arithmetic(value, nonzero(self.unary()?)?, i32::checked_rem)?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Value, ErrorType> {
+        match self.eat('-') {
+            true => arithmetic(Value::Number(0), self.unary()?, i32::checked_sub),
+            false => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Value, ErrorType> {
+        if self.eat('(') {
+            let value = self.expr()?;
+            return match self.eat(')') {
+                true => Ok(value),
+                false => Err(ErrorType::InvalidExpression),
+            };
+        }
+        let delimiter = self.scope.keyword_delimiter;
+        if self.eat(delimiter) {
+            let start = self.pos - 1;
+            loop {
+                match self.chars.get(self.pos) {
+                    None => return Err(ErrorType::UnterminatedKeyword),
+                    Some('\\') => self.pos += 2,
+                    Some(c) if *c == delimiter => break,
+                    Some(_) => self.pos += 1,
+                }
+            }
+            self.pos += 1;
+            let literal: String = self.chars[start..self.pos].iter().collect();
+            return Ok(Value::Keyword(parse_keyword(&literal, delimiter)?));
+        }
+        let start = self.pos;
+        while self
+            .chars
+            .get(self.pos)
+            .is_some_and(|c| c.is_alphanumeric() || *c == '_')
+        {
+            self.pos += 1;
+        }
+        let word: String = self.chars[start..self.pos].iter().collect();
+        if word.is_empty() {
+            return Err(ErrorType::InvalidExpression);
+        }
+        if word.chars().next().unwrap().is_ascii_digit() {
+            return match word.parse() {
+                Ok(n) => Ok(Value::Number(n)),
+                Err(_) => Err(ErrorType::InvalidNumber),
+            };
+        }
+        if let Some(level) = counter_level(&word) {
+            return Ok(Value::Number(counter(self.scope, level)?));
+        }
+        match self.scope.constants.get(&word) {
+            Some(t) if t.ttype == TokenType::Keyword => Ok(Value::Keyword(parse_keyword(
+                &t.content,
+                self.scope.keyword_delimiter,
+            )?)),
+            Some(t) => t
+                .content
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| ErrorType::InvalidNumber),
+            None => Err(ErrorType::UndefinedConstant),
+        }
+    }
+}
+
+/// `I` is level 0, `I1` is level 1, and so on
+fn counter_level(word: &str) -> Option<usize> {
+    let rest = word.strip_prefix(['i', 'I'])?;
+    match rest.is_empty() {
+        true => Some(0),
+        false => rest.parse().ok(),
+    }
+}
+
+fn add(a: Value, b: Value) -> Result<Value, ErrorType> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a
+            .checked_add(b)
+            .map(Value::Number)
+            .ok_or(ErrorType::NumberOutOfRange),
+        (a, b) => Ok(Value::Keyword(format!("{}{}", text(a), text(b)))),
+    }
+}
+
+fn text(v: Value) -> String {
+    match v {
+        Value::Number(n) => n.to_string(),
+        Value::Keyword(k) => k,
+    }
+}
+
+fn arithmetic<F>(a: Value, b: Value, op: F) -> Result<Value, ErrorType>
+where
+    F: Fn(i32, i32) -> Option<i32>,
+{
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => op(a, b)
+            .map(Value::Number)
+            .ok_or(ErrorType::NumberOutOfRange),
+        _ => Err(ErrorType::InvalidExpression),
+    }
+}
+
+/// dividing by zero is an invalid expression, rather than an overflow
+fn nonzero(v: Value) -> Result<Value, ErrorType> {
+    match v {
+        Value::Number(0) => Err(ErrorType::InvalidExpression),
+        v => Ok(v),
+    }
+}
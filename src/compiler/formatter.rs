@@ -0,0 +1,100 @@
+use crate::compiler::{parse_keyword, Compiler, Line, Token, TokenType};
+use crate::exa::{escape_keyword, OpCode};
+
+const INDENT: &str = "    ";
+
+impl Compiler {
+    /// lays out EXA source canonically
+    ///
+    /// - opcodes and registers are upper-cased, directives are lower-cased
+    /// - tokens are separated by a single space, and trailing whitespace is removed
+    /// - `@rep` and `@def` bodies are indented
+    /// - comments use the first configured prefix, followed by a single space
+    /// - keywords are re-escaped, so `'a\sb'` becomes `'a b'`
+    ///
+    /// everything else (labels, macro arguments) is kept verbatim,
+    /// so the formatted source compiles to the same program
+    pub fn format(&self, raw: &[&str]) -> String {
+        let lines = self.lex(raw);
+        let mut lines = lines.iter().peekable();
+        let mut depth: usize = 0;
+        let mut out = String::new();
+        for row in 0..raw.len() {
+            let line = match lines.peek() {
+                Some(l) if l.row() == row => lines.next().unwrap(),
+                _ => {
+                    out.push('\n');
+                    continue;
+                }
+            };
+            let first = line.first().and_then(|t| t.as_ref().ok());
+            let directive = first.map(|t| t.content.to_lowercase());
+            if directive.as_deref() == Some("@end") {
+                depth = depth.saturating_sub(1);
+            }
+            for _ in 0..depth {
+                out.push_str(INDENT);
+            }
+            out.push_str(&self.format_line(line));
+            out.push('\n');
+            if matches!(directive.as_deref(), Some("@rep") | Some("@def")) {
+                depth += 1;
+            }
+        }
+        out
+    }
+
+    fn format_line(&self, line: &Line) -> String {
+        let mut words: Vec<String> = line
+            .iter()
+            .filter_map(|t| t.as_ref().ok())
+            .map(|t| self.format_token(t))
+            .collect();
+        if let Some(comment) = line.comment.as_ref() {
+            words.push(self.format_comment(comment, line.is_empty()));
+        }
+        words.join(" ")
+    }
+
+    fn format_comment(&self, comment: &Token, line_start: bool) -> String {
+        let prefixes = match line_start {
+            true => &self.comment_prefixes,
+            false => &self.inline_comment_prefixes,
+        };
+        let lower = comment.content.to_lowercase();
+        let matched = prefixes
+            .iter()
+            .filter(|p| lower.starts_with(&p.to_lowercase()))
+            .max_by_key(|p| p.len());
+        let (canonical, matched) = match (prefixes.first(), matched) {
+            (Some(c), Some(m)) => (c, m),
+            _ => return comment.content.trim_end().to_string(),
+        };
+        let text = comment.content[matched.len()..].trim();
+        match text.is_empty() {
+            true => canonical.to_string(),
+            false => format!("{} {}", canonical, text),
+        }
+    }
+
+    fn format_token(&self, t: &Token) -> String {
+        match t.ttype {
+            TokenType::OpCode => match t.content.parse::<OpCode>() {
+                Ok(op) => op.to_string(),
+                Err(_) => match t.content.starts_with('@') {
+                    true => t.content.to_lowercase(),
+                    false => t.content.clone(),
+                },
+            },
+            TokenType::MacroStart | TokenType::MacroEnd | TokenType::ConstDef => {
+                t.content.to_lowercase()
+            }
+            TokenType::RegisterLabel if !t.content.starts_with('#') => t.content.to_uppercase(),
+            TokenType::Keyword => match parse_keyword(&t.content, self.keyword_delimiter) {
+                Ok(keyword) => escape_keyword(&keyword, self.keyword_delimiter),
+                Err(_) => t.content.clone(),
+            },
+            _ => t.content.clone(),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::compiler::{Compiler, ErrorType, Line, TokenType};
+
+/// what a highlighted span of source is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanKind {
+    Token(TokenType),
+    /// a range the compiler rejected, overlapping the token spans it covers
+    Error(ErrorType),
+}
+
+/// a classified range of a single source line, columns and length are in characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub row: usize,
+    pub col: usize,
+    pub len: usize,
+    pub kind: SpanKind,
+}
+
+impl Compiler {
+    /// classifies every token, comment and error range in `raw`, sorted by position
+    ///
+    /// token spans come from lexing each line on its own, so they never overlap each other,
+    /// error spans come from a full compile, and only cover code in `raw` itself
+    pub fn highlight(&self, raw: &[&str]) -> Vec<Span> {
+        let lines = self.lex(raw);
+        let mut spans = Vec::new();
+        for line in lines.iter() {
+            for t in line.iter() {
+                spans.push(match t {
+                    Ok(t) => Span {
+                        row: t.row,
+                        col: t.col,
+                        len: t.content.chars().count(),
+                        kind: SpanKind::Token(t.ttype),
+                    },
+                    Err(e) => Span {
+                        row: e.row,
+                        col: e.col,
+                        len: e.context.chars().count(),
+                        kind: SpanKind::Error(e.etype),
+                    },
+                });
+            }
+            if let Some(c) = &line.comment {
+                spans.push(Span {
+                    row: c.row,
+                    col: c.col,
+                    len: c.content.chars().count(),
+                    kind: SpanKind::Token(TokenType::Comment),
+                });
+            }
+        }
+
+        if let Err(errs) = self.compile_program(raw) {
+            for e in errs.iter().filter(|e| e.file.is_none()) {
+                let span = Span {
+                    row: e.row,
+                    col: e.col,
+                    len: error_len(&lines, raw, e.row, e.col),
+                    kind: SpanKind::Error(e.etype),
+                };
+                if !spans.contains(&span) {
+                    spans.push(span);
+                }
+            }
+        }
+        spans.sort_by_key(|s| (s.row, s.col, matches!(s.kind, SpanKind::Error(_))));
+        spans
+    }
+}
+
+/// length of the token starting at `row`, `col`, or of the word there if nothing was lexed
+fn error_len(lines: &[Line], raw: &[&str], row: usize, col: usize) -> usize {
+    let token = lines
+        .iter()
+        .find(|l| l.row() == row)
+        .and_then(|l| l.iter().flatten().find(|t| t.col == col));
+    let len = match token {
+        Some(t) => t.content.chars().count(),
+        None => raw
+            .get(row)
+            .map(|l| {
+                l.chars()
+                    .skip(col)
+                    .take_while(|c| !c.is_whitespace())
+                    .count()
+            })
+            .unwrap_or(0),
+    };
+    usize::max(len, 1)
+}
@@ -0,0 +1,147 @@
+use crate::compiler::cfg::Cfg;
+use crate::compiler::Program;
+use crate::exa::{Arg, Instruction, OpCode, RegLabel};
+
+/// peephole optimizer for compiled programs
+///
+/// removes or folds:
+/// - `COPY X X` and `COPY T T`
+/// - `NOOP`s (this changes cycle counts, so don't use it on timing-sensitive exas)
+/// - `JUMP`, `TJMP` and `FJMP` to the next instruction
+/// - arithmetic identities such as `ADDI X 0 X` or `MULI 1 X T` (folded into a `COPY`)
+/// - blocks of the control-flow graph no path from the start reaches
+///
+/// reachable instructions touching `M`, `F` or hardware registers are never removed,
+/// since reading or writing them is observable.
+/// folding arithmetic assumes the operands are numeric,
+/// so a keyword that would have errored with `NumericValueRequired` is copied instead
+pub fn optimize(mut program: Program) -> Program {
+    loop {
+        let cfg = Cfg::new(&program);
+        let reachable = cfg.reachable();
+        let mut keep: Vec<bool> = (0..program.len())
+            .map(|x| reachable[cfg.block_of(x)])
+            .collect();
+        let mut changed = keep.contains(&false);
+        for (x, instr) in program.instructions.iter_mut().enumerate() {
+            match rewrite(instr, x) {
+                Rewrite::Keep => (),
+                Rewrite::Remove => {
+                    keep[x] = false;
+                    changed = true;
+                }
+                Rewrite::Replace(new_instr) => {
+                    *instr = new_instr;
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return program;
+        }
+        program = remove_instructions(program, &keep);
+    }
+}
+
+enum Rewrite {
+    Keep,
+    Remove,
+    Replace(Instruction),
+}
+
+fn rewrite(instr: &Instruction, index: usize) -> Rewrite {
+    match instr {
+        Instruction(OpCode::Noop, ..) => Rewrite::Remove,
+        Instruction(OpCode::Copy, Some(a), Some(b), _) if a == b && is_plain_reg(a) => {
+            Rewrite::Remove
+        }
+        Instruction(OpCode::Jump | OpCode::Tjmp | OpCode::Fjmp, Some(Arg::JumpIndex(j)), ..)
+            if *j as usize == index + 1 =>
+        {
+            Rewrite::Remove
+        }
+        Instruction(op, Some(a), Some(b), Some(target)) if is_plain_reg(target) => {
+            let source = match op {
+                OpCode::Addi if is_number(b, 0) => a,
+                OpCode::Addi if is_number(a, 0) => b,
+                OpCode::Subi if is_number(b, 0) => a,
+                OpCode::Muli if is_number(b, 1) => a,
+                OpCode::Muli if is_number(a, 1) => b,
+                OpCode::Divi if is_number(b, 1) => a,
+                _ => return Rewrite::Keep,
+            };
+            if !is_plain_reg(source) {
+                Rewrite::Keep
+            } else if source == target {
+                Rewrite::Remove
+            } else {
+                Rewrite::Replace(Instruction(
+                    OpCode::Copy,
+                    Some(source.clone()),
+                    Some(target.clone()),
+                    None,
+                ))
+            }
+        }
+        _ => Rewrite::Keep,
+    }
+}
+
+/// `X` and `T`, the only registers that can be read and written without side effects
+fn is_plain_reg(arg: &Arg) -> bool {
+    matches!(arg, Arg::RegLabel(RegLabel::X) | Arg::RegLabel(RegLabel::T))
+}
+
+fn is_number(arg: &Arg, n: i16) -> bool {
+    *arg == Arg::Number(n)
+}
+
+/// drops every instruction not marked in `keep`, retargeting jumps, labels and the source map
+fn remove_instructions(program: Program, keep: &[bool]) -> Program {
+    // maps old indices to new ones, a removed instruction maps to the one after it
+    let mut new_index = Vec::with_capacity(keep.len() + 1);
+    let mut kept = 0;
+    for k in keep.iter() {
+        new_index.push(kept);
+        if *k {
+            kept += 1;
+        }
+    }
+    new_index.push(kept);
+
+    let retarget = |arg: Option<Arg>| match arg {
+        Some(Arg::JumpIndex(j)) => Some(Arg::JumpIndex(new_index[j as usize] as u16)),
+        a => a,
+    };
+
+    let instructions = program
+        .instructions
+        .into_vec()
+        .into_iter()
+        .zip(keep.iter())
+        .filter(|(_, k)| **k)
+        .map(|(Instruction(op, a, b, c), _)| Instruction(op, retarget(a), b, c))
+        .collect();
+    let source_map = program
+        .source_map
+        .into_vec()
+        .into_iter()
+        .zip(keep.iter())
+        .filter(|(_, k)| **k)
+        .map(|(l, _)| l)
+        .collect();
+    let labels = program
+        .labels
+        .into_iter()
+        .map(|mut l| {
+            l.index = new_index[l.index];
+            l
+        })
+        .collect();
+    Program {
+        instructions,
+        source_map,
+        labels,
+        ..program
+    }
+}
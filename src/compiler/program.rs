@@ -0,0 +1,56 @@
+use std::rc::Rc;
+
+use crate::exa::{Instruction, Metadata};
+
+/// where a piece of compiled code came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: Option<Rc<str>>,
+    pub row: usize,
+    pub col: usize,
+    /// characters from `col` to the end of the last token on the row, at least 1
+    pub len: usize,
+}
+
+/// a `MARK` removed during compilation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub name: String,
+    /// index of the instruction the label points to
+    pub index: usize,
+    pub location: SourceLocation,
+    /// number of `JUMP`s, `TJMP`s, `FJMP`s and `REPL`s targeting the label
+    pub uses: usize,
+}
+
+/// a compiled program, along with the information needed to map it back to its source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub instructions: Box<[Instruction]>,
+    /// source location of each instruction, indexed the same as `instructions`
+    pub source_map: Box<[SourceLocation]>,
+    pub labels: Vec<Label>,
+    pub metadata: Metadata,
+    /// size as the game counts it: instructions after `@rep` expansion, without `MARK`s and
+    /// comments, and before optimization
+    pub size: usize,
+}
+
+impl Program {
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    /// index of the instruction execution starts at, set by the `@entry` directive
+    pub fn entry(&self) -> usize {
+        self.metadata
+            .entry
+            .as_ref()
+            .and_then(|e| self.labels.iter().find(|l| &l.name == e))
+            .map_or(0, |l| l.index)
+    }
+}
@@ -7,13 +7,74 @@ mod vm_config;
 use crate::compiler::config::Config as CompilerConfig;
 pub use vm_config::VMConfig;
 
+/// a host's configuration, read from `hosts/config.toml`
+///
+/// the compiler configuration is either a full `[compiler_config]` table, or a `profile`
+/// (see `compiler::config::PROFILES`) with `enabled_instructions`, `disabled_instructions`,
+/// `enabled_comparisons` and `disabled_comparisons` lists added on top of it
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "HostConfigFile")]
 pub struct HostConfig {
     pub hostname: Rc<Box<str>>,
+    /// profile the compiler configuration was built from, it's written back out in full
+    #[serde(skip_serializing)]
+    pub profile: Option<String>,
     pub compiler_config: Rc<CompilerConfig>,
     pub vm_config: Rc<VMConfig>,
 }
 
+/// `HostConfig` as written, before its profile is looked up
+#[derive(Deserialize)]
+struct HostConfigFile {
+    hostname: Rc<Box<str>>,
+    profile: Option<String>,
+    compiler_config: Option<CompilerConfig>,
+    #[serde(default)]
+    enabled_instructions: Vec<String>,
+    #[serde(default)]
+    disabled_instructions: Vec<String>,
+    #[serde(default)]
+    enabled_comparisons: Vec<String>,
+    #[serde(default)]
+    disabled_comparisons: Vec<String>,
+    vm_config: Rc<VMConfig>,
+}
+
+impl TryFrom<HostConfigFile> for HostConfig {
+    type Error = String;
+
+    fn try_from(file: HostConfigFile) -> Result<Self, Self::Error> {
+        let mut compiler_config = match (&file.profile, file.compiler_config) {
+            (Some(p), None) => {
+                CompilerConfig::profile(p).ok_or_else(|| format!("unknown profile '{}'", p))?
+            }
+            (None, Some(c)) => c,
+            (Some(_), Some(_)) => {
+                return Err("set either `profile` or `[compiler_config]`, not both".to_string())
+            }
+            (None, None) => return Err("missing `profile` or `[compiler_config]`".to_string()),
+        };
+        compiler_config
+            .enabled_instructions
+            .extend(file.enabled_instructions);
+        compiler_config
+            .disabled_instructions
+            .extend(file.disabled_instructions);
+        compiler_config
+            .enabled_comparisons
+            .extend(file.enabled_comparisons);
+        compiler_config
+            .disabled_comparisons
+            .extend(file.disabled_comparisons);
+        Ok(Self {
+            hostname: file.hostname,
+            profile: file.profile,
+            compiler_config: compiler_config.into(),
+            vm_config: file.vm_config,
+        })
+    }
+}
+
 impl HostConfig {
     pub fn new(
         hostname: Rc<Box<str>>,
@@ -22,10 +83,17 @@ impl HostConfig {
     ) -> Self {
         Self {
             hostname,
+            profile: None,
             compiler_config,
             vm_config,
         }
     }
+
+    /// reads a host configuration from a TOML file, `None` if it's missing or invalid
+    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> Option<Self> {
+        let s = std::fs::read_to_string(path).ok()?;
+        toml::from_str(&s).ok()
+    }
 }
 
 impl Default for HostConfig {
@@ -4,6 +4,9 @@ use serde::{Deserialize, Serialize};
 pub struct VMConfig {
     pub max_exas: usize,
     pub max_files: usize,
+    /// count how often each instruction executes and blocks, see `vm::profile`
+    #[serde(default)]
+    pub profile: bool,
 }
 
 impl VMConfig {
@@ -11,6 +14,7 @@ impl VMConfig {
         Self {
             max_exas,
             max_files,
+            profile: false,
         }
     }
 }
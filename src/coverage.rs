@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+
+use crate::compiler::Program;
+use crate::exa::OpCode;
+use crate::vm::profile::ExaProfile;
+
+/// which source lines and `TJMP`/`FJMP` branches ran, collected from the profiles
+/// of any number of runs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    files: BTreeMap<String, FileCoverage>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct FileCoverage {
+    /// hits by row
+    lines: BTreeMap<usize, usize>,
+    /// times each conditional jump ran and jumped, by row and instruction index
+    branches: BTreeMap<(usize, usize), (usize, usize)>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// adds what a run of `program` executed, `None` if the exa never ran.
+    /// `file` names the source the program was passed in as directly, code from files
+    /// (like `@include`s) is attributed to the file it came from
+    pub fn add(&mut self, program: &Program, file: &str, profile: Option<&ExaProfile>) {
+        let empty = ExaProfile::default();
+        let profile = profile.unwrap_or(&empty);
+        for (i, location) in program.source_map.iter().enumerate() {
+            let coverage = self
+                .files
+                .entry(location.file.as_deref().unwrap_or(file).to_string())
+                .or_default();
+            *coverage.lines.entry(location.row).or_insert(0) += profile.executed(i);
+            if matches!(program.instructions[i].0, OpCode::Tjmp | OpCode::Fjmp) {
+                let branch = coverage.branches.entry((location.row, i)).or_insert((0, 0));
+                branch.0 += profile.executed(i);
+                branch.1 += profile.taken(i);
+            }
+        }
+    }
+
+    /// adds everything another coverage collected
+    pub fn merge(&mut self, other: &Coverage) {
+        for (file, more) in other.files.iter() {
+            let coverage = self.files.entry(file.clone()).or_default();
+            for (row, hits) in more.lines.iter() {
+                *coverage.lines.entry(*row).or_insert(0) += hits;
+            }
+            for (key, (executed, taken)) in more.branches.iter() {
+                let branch = coverage.branches.entry(*key).or_insert((0, 0));
+                branch.0 += executed;
+                branch.1 += taken;
+            }
+        }
+    }
+
+    /// the coverage as an lcov tracefile, with a record per source file.
+    /// each conditional jump is a block of two branches, 0 for jumping and 1 for falling through
+    pub fn to_lcov(&self) -> String {
+        let mut out = String::new();
+        for (file, coverage) in self.files.iter() {
+            out.push_str(&format!("TN:\nSF:{}\n", file));
+            let mut branches_hit = 0;
+            for ((row, index), (executed, taken)) in coverage.branches.iter() {
+                for (branch, count) in [*taken, executed - taken].into_iter().enumerate() {
+                    let count = match executed {
+                        0 => "-".to_string(),
+                        _ => count.to_string(),
+                    };
+                    out.push_str(&format!(
+                        "BRDA:{},{},{},{}\n",
+                        row + 1,
+                        index,
+                        branch,
+                        count
+                    ));
+                }
+                branches_hit += (*taken > 0) as usize + (executed - taken > 0) as usize;
+            }
+            out.push_str(&format!(
+                "BRF:{}\nBRH:{}\n",
+                coverage.branches.len() * 2,
+                branches_hit
+            ));
+            for (row, hits) in coverage.lines.iter() {
+                out.push_str(&format!("DA:{},{}\n", row + 1, hits));
+            }
+            out.push_str(&format!(
+                "LF:{}\nLH:{}\nend_of_record\n",
+                coverage.lines.len(),
+                coverage.lines.values().filter(|h| **h > 0).count()
+            ));
+        }
+        out
+    }
+
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), std::io::Error> {
+        std::fs::write(path, self.to_lcov())
+    }
+}
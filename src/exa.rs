@@ -1,28 +1,35 @@
 use serde::{Deserialize, Serialize};
 
+use crate::compiler::Program;
 use crate::file::File;
 
 mod arg;
 mod instruction;
+mod metadata;
 mod register;
 
-pub use arg::{Arg, Comp, RegLabel};
+pub use arg::{escape_keyword, Arg, Comp, RegLabel};
 pub use instruction::{Instruction, OpCode};
+pub use metadata::{MemoryMode, Metadata, Requirement};
 pub use register::Register;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Exa {
     pub name: String,
     pub instr_list: Box<[Instruction]>,
-    pub instr_ptr: u8,
+    pub instr_ptr: u16,
     pub repl_counter: usize,
     pub reg_x: Register,
     pub reg_t: Register,
     pub reg_f: Option<(i16, File)>,
+    pub metadata: Metadata,
+    /// size of the program, as the game counts it
+    pub size: usize,
 }
 
 impl Exa {
     pub fn new(name: &str, instr_list: Box<[Instruction]>) -> Self {
+        let size = instr_list.iter().filter(|i| i.0 != OpCode::Mark).count();
         Self {
             name: name.to_string(),
             instr_list,
@@ -31,6 +38,18 @@ impl Exa {
             reg_x: Register::Number(0),
             reg_t: Register::Number(0),
             reg_f: None,
+            metadata: Metadata::default(),
+            size,
         }
     }
+
+    /// creates an exa starting at the program's entry point, carrying its metadata
+    pub fn from_program(name: &str, program: Program) -> Self {
+        let entry = program.entry() as u16;
+        let mut exa = Self::new(name, program.instructions);
+        exa.instr_ptr = entry;
+        exa.metadata = program.metadata;
+        exa.size = program.size;
+        exa
+    }
 }
@@ -8,7 +8,7 @@ pub enum Arg {
     Number(i16),
     Comp(Comp),
     Keyword(Box<str>),
-    JumpIndex(u8),
+    JumpIndex(u16),
 }
 
 impl Arg {
@@ -33,7 +33,7 @@ impl Arg {
         }
     }
 
-    pub fn jump_index(&self) -> Result<u8, &str> {
+    pub fn jump_index(&self) -> Result<u16, &str> {
         match self {
             Self::JumpIndex(j) => Ok(*j),
             _ => Err("arg is not JumpIndex"),
@@ -53,7 +53,7 @@ impl Display for Arg {
         match self {
             Self::Comp(c) => write!(f, "{}", c),
             Self::Number(n) => write!(f, "{}", n),
-            Self::Keyword(w) => write!(f, "{}", w),
+            Self::Keyword(w) => write!(f, "{}", escape_keyword(w, '\'')),
             Self::RegLabel(r) => write!(f, "{}", r),
             Self::JumpIndex(l) => write!(f, "{}", l),
         }
@@ -136,3 +136,18 @@ impl Display for Comp {
         }
     }
 }
+
+/// quotes a keyword with `delimiter`, escaping backslashes and the delimiter,
+/// so it reads back as the same keyword
+pub fn escape_keyword(keyword: &str, delimiter: char) -> String {
+    let mut literal = String::with_capacity(keyword.len() + 2);
+    literal.push(delimiter);
+    for c in keyword.chars() {
+        if c == '\\' || c == delimiter {
+            literal.push('\\');
+        }
+        literal.push(c);
+    }
+    literal.push(delimiter);
+    literal
+}
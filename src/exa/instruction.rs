@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::{fmt::Display, str::FromStr};
 
-use crate::exa::arg::Arg;
+use crate::exa::arg::{escape_keyword, Arg};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Instruction(
@@ -35,6 +35,25 @@ impl Instruction {
     pub fn arg_refs(&self) -> (Option<&Arg>, Option<&Arg>, Option<&Arg>) {
         (self.1.as_ref(), self.2.as_ref(), self.3.as_ref())
     }
+
+    /// renders the instruction as source, quoting keywords with `delimiter`
+    pub fn disassemble(&self, delimiter: char) -> String {
+        let mut out = self.0.to_string();
+        for arg in [&self.1, &self.2, &self.3].into_iter().flatten() {
+            out.push(' ');
+            match arg {
+                Arg::Keyword(k) => out.push_str(&escape_keyword(k, delimiter)),
+                a => out.push_str(&a.to_string()),
+            }
+        }
+        out
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.disassemble('\''))
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -226,6 +245,94 @@ pub enum OpCode {
     Prnt,
 }
 
+impl OpCode {
+    /// documentation for the instruction, mirroring the doc comments above, for editor tooling
+    pub fn docs(&self) -> &'static str {
+        match self {
+            Self::Copy => "`COPY value: R/N target: R`\n\ncopies `value` into `target`",
+            Self::Void => {
+                "`VOID target: R`\n\nclears target by:\n\
+                 - setting `X` and `T` to default values (0)\n\
+                 - taking and discarding the value in `M`\n\
+                 - blanking the value in `F`"
+            }
+            Self::Addi => {
+                "`ADDI num1: R/N num2: R/N target: R`\n\n\
+                 performs `num1 + num2`, and puts the result in `target`\n\n\
+                 Errors:\n- `num1` or `num2` are not numeric values"
+            }
+            Self::Subi => {
+                "`SUBI num1: R/N num2: R/N target: R`\n\n\
+                 performs `num1 - num2`, and puts the result in `target`\n\n\
+                 Errors:\n- `num1` or `num2` are not numeric values"
+            }
+            Self::Muli => {
+                "`MULI num1: R/N num2: R/N target: R`\n\n\
+                 performs `num1 * num2`, and puts the result in `target`\n\n\
+                 Errors:\n- `num1` or `num2` are not numeric values"
+            }
+            Self::Divi => {
+                "`DIVI num1: R/N num2: R/N target: R`\n\n\
+                 performs `num1 / num2`, and puts the result in `target`\n\n\
+                 Errors:\n- `num1` or `num2` are not numeric values"
+            }
+            Self::Modi => {
+                "`MODI num1: R/N num2: R/N target: R`\n\n\
+                 performs `num1 % num2`, and puts the result in `target`\n\n\
+                 Errors:\n- `num1` or `num2` are not numeric values"
+            }
+            Self::Swiz => "`SWIZ num1: R/N num2: R/N target: R`",
+            Self::Mode => "`MODE`\n\ndoesn't work",
+            Self::Test => {
+                "`TEST val1: R/N comp: C val2: R/N`\n\n\
+                 performs `val1 comp val2`, and puts the result in `T`\n\n\
+                 basic comparison operators: `>`, `<`, `=`\n\n\
+                 extended comparison operators: `>`, `<`, `=`, `>=`, `<=`, `!=`"
+            }
+            Self::TestMrd => {
+                "`TEST MRD`\n\nchecks if `M` can be read without blocking, and puts result into `T`"
+            }
+            Self::TestEof => {
+                "`TEST EOF`\n\n\
+                 checks if `F` is at the end of the held file, and puts result into `T`\n\n\
+                 Errors:\n- file not held"
+            }
+            Self::Mark => {
+                "`MARK label: L`\n\nPseudo-instruction, target of `JUMP` and `REPL` instructions"
+            }
+            Self::Jump => "`JUMP label: L`\n\njumps to `label`",
+            Self::Fjmp => "`FJMP label: L`\n\njumps to `label` if `T` is 0",
+            Self::Tjmp => "`TJMP label: L`\n\njumps to `label` if `T` is not 0",
+            Self::Make => "`MAKE`\n\ncreates a file\n\nErrors:\n- already holding file",
+            Self::Grab => "`GRAB id: R/N`\n\ngrabs file with `id`\n\nErrors:\n- already holding file",
+            Self::File => "`FILE target: R`\n\nputs id of held file into `target`\n\nErrors:\n- file not held",
+            Self::Seek => {
+                "`SEEK amount: R/N`\n\n\
+                 moves `F` forward in held file by `amount` (or backwards if `amount` is negative)\n\n\
+                 Errors:\n- file not held"
+            }
+            Self::Drop => {
+                "`DROP`\n\ndrops held file\n\n\
+                 Blocks until there is space for the file\n\n\
+                 Errors:\n- file not held"
+            }
+            Self::Wipe => "`WIPE`\n\ndeletes held file\n\nErrors:\n- file not held",
+            Self::Link => "`LINK id: R/N`\n\nUNIMPLEMENTED!",
+            Self::Repl => {
+                "`REPL label: L`\n\n\
+                 spawns a copy of the EXA, starting execution at `label`\n\n\
+                 Blocks until there is space for the copy"
+            }
+            Self::Halt => "`HALT`\n\ndestroys EXA",
+            Self::Kill => "`KILL`\n\ndestroys another EXA in the current host",
+            Self::Rand => "`RAND num1: R/N num2: R/N target: R`",
+            Self::Host => "`HOST target: R`\n\nputs the name of the current host into `target`",
+            Self::Noop => "`NOOP`\n\ndoes nothing for 1 cycle",
+            Self::Prnt => "`PRNT value: R/N`",
+        }
+    }
+}
+
 impl FromStr for OpCode {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -246,6 +353,7 @@ impl FromStr for OpCode {
             "jump" => Ok(Self::Jump),
             "fjmp" => Ok(Self::Fjmp),
             "tjmp" => Ok(Self::Tjmp),
+            "make" => Ok(Self::Make),
             "grab" => Ok(Self::Grab),
             "file" => Ok(Self::File),
             "seek" => Ok(Self::Seek),
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+use crate::exa::OpCode;
+
+/// something a host has to provide for an exa to run there
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Requirement {
+    /// a hardware register, like `#NERV`
+    HardwareRegister(String),
+    /// an instruction outside the game's instruction set, like `PRNT`
+    Instruction(OpCode),
+}
+
+impl std::fmt::Display for Requirement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::HardwareRegister(r) => write!(f, "{}", r),
+            Self::Instruction(op) => write!(f, "{}", op),
+        }
+    }
+}
+
+/// how an exa's M register starts out, toggled by `MODE`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MemoryMode {
+    #[default]
+    Global,
+    Local,
+}
+
+impl std::str::FromStr for MemoryMode {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_lowercase()[..] {
+            "global" => Ok(Self::Global),
+            "local" => Ok(Self::Local),
+            _ => Err(()),
+        }
+    }
+}
+
+impl std::fmt::Display for MemoryMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Global => write!(f, "global"),
+            Self::Local => write!(f, "local"),
+        }
+    }
+}
+
+/// information about a program, set by header directives and carried along with the bytecode
+///
+/// ```text
+/// @name SORTER
+/// @author 'Ghast'
+/// @description sorts the file it's handed
+/// @entry START
+/// @mode local
+/// @requires #NERV PRNT
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Metadata {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    /// label execution starts at, instead of the first instruction
+    pub entry: Option<String>,
+    /// the mode the exa's `M` register starts in when it runs in the game,
+    /// kept for `.solution` files, hosts ignore it
+    pub mode: Option<MemoryMode>,
+    pub requires: Vec<Requirement>,
+}
@@ -32,6 +32,10 @@ impl File {
         self.ptr == self.content.len() as i16
     }
 
+    pub fn content(&self) -> &[Register] {
+        &self.content
+    }
+
     pub fn new() -> Self {
         Self {
             content: Vec::new(),
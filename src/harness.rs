@@ -0,0 +1,435 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::compiler::{config::Config as CompilerConfig, Compiler, Error, Program};
+use crate::config::{HostConfig, VMConfig};
+use crate::coverage::Coverage;
+use crate::exa::{Exa, Register};
+use crate::file::File;
+use crate::metrics::Metrics;
+use crate::{DeployError, Host};
+
+/// a register value in a test case, written as a number or a string
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Number(i64),
+    Text(String),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Text(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+fn file(values: &[Value]) -> File {
+    let strings: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    File::from(strings.iter().map(|s| &s[..]).collect::<Vec<&str>>())
+}
+
+/// converts values the same way `File::from` does
+fn registers(values: &[Value]) -> Vec<Register> {
+    file(values).content().to_vec()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileSpec {
+    pub id: i16,
+    pub content: Vec<Value>,
+}
+
+/// an exa to deploy, from inline `source` or a `file` relative to the test case
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExaSpec {
+    /// defaults to the exa's `@name`, or else the file name without the extension
+    pub name: Option<String>,
+    pub source: Option<String>,
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Input {
+    /// values sent through `M`, one whenever it's free
+    #[serde(default)]
+    pub m: Vec<Value>,
+    /// values read from each hardware register, like `#NERV`
+    #[serde(default)]
+    pub hardware: BTreeMap<String, Vec<Value>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expect {
+    /// contents of files left on the host
+    #[serde(default)]
+    pub files: Vec<FileSpec>,
+    /// values `PRNT`ed, in order
+    pub output: Option<Vec<Value>>,
+    /// number of exas still running at the end, `None` requires all of them to finish
+    pub exas: Option<usize>,
+}
+
+fn default_cycles() -> usize {
+    10000
+}
+
+/// a test of EXA programs, read from TOML or YAML
+///
+/// ```toml
+/// cycles = 100
+///
+/// [[files]]
+/// id = 200
+/// content = [3, "BOB"]
+///
+/// [[exas]]
+/// name = "XA"
+/// source = """
+/// GRAB 200
+/// COPY F X
+/// PRNT X
+/// """
+///
+/// [expect]
+/// output = [3]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestCase {
+    /// defaults to the file name without the extension
+    pub name: Option<String>,
+    /// compiler profile, see `compiler::config::PROFILES`, defaults to `extended`
+    pub profile: Option<String>,
+    /// cycles to run for at most
+    #[serde(default = "default_cycles")]
+    pub cycles: usize,
+    #[serde(default)]
+    pub files: Vec<FileSpec>,
+    pub exas: Vec<ExaSpec>,
+    #[serde(default)]
+    pub input: Input,
+    #[serde(default)]
+    pub expect: Expect,
+    /// where the case was read from, exa files are relative to it
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+/// why a test case failed
+#[derive(Debug)]
+pub enum Failure {
+    /// the case file couldn't be read or parsed
+    Unreadable(String),
+    /// the case itself is wrong, like an exa without a source
+    Invalid(String),
+    UnknownProfile(String),
+    Compile(Vec<Error>),
+    Deploy(String, DeployError),
+    /// exas were still running after the cycle limit
+    CycleLimit(usize),
+    Output {
+        expected: Vec<Register>,
+        actual: Vec<Register>,
+    },
+    File {
+        id: i16,
+        expected: Vec<Register>,
+        /// `None` if the file isn't on the host
+        actual: Option<Vec<Register>>,
+    },
+    ExasRemaining {
+        expected: usize,
+        actual: usize,
+    },
+}
+
+fn join(values: &[Register]) -> String {
+    let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", values.join(", "))
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unreadable(e) => write!(f, "unreadable test case: {}", e),
+            Self::Invalid(e) => write!(f, "invalid test case: {}", e),
+            Self::UnknownProfile(p) => write!(f, "unknown profile {}", p),
+            Self::Compile(errs) => {
+                write!(f, "compilation failed")?;
+                for e in errs {
+                    write!(
+                        f,
+                        "\n  {}:{}:{}: {:?}: {}",
+                        e.file.as_deref().unwrap_or("<source>"),
+                        e.row + 1,
+                        e.col + 1,
+                        e.etype,
+                        e.context
+                    )?;
+                }
+                Ok(())
+            }
+            Self::Deploy(exa, e) => write!(f, "couldn't deploy {}: {:?}", exa, e),
+            Self::CycleLimit(n) => write!(f, "exas still running after {} cycles", n),
+            Self::Output { expected, actual } => write!(
+                f,
+                "output was {}, expected {}",
+                join(actual),
+                join(expected)
+            ),
+            Self::File {
+                id,
+                expected,
+                actual: Some(actual),
+            } => write!(
+                f,
+                "file {} was {}, expected {}",
+                id,
+                join(actual),
+                join(expected)
+            ),
+            Self::File { id, expected, .. } => {
+                write!(f, "file {} missing, expected {}", id, join(expected))
+            }
+            Self::ExasRemaining { expected, actual } => {
+                write!(f, "{} exas remaining, expected {}", actual, expected)
+            }
+        }
+    }
+}
+
+/// the outcome of running a test case
+#[derive(Debug)]
+pub struct Report {
+    pub name: String,
+    pub failures: Vec<Failure>,
+    pub metrics: Metrics,
+    /// what the exas executed, keyed by the file each came from,
+    /// or the test case and exa name for inline sources
+    pub coverage: Coverage,
+}
+
+impl Report {
+    fn failed(name: String, failure: Failure) -> Self {
+        Self {
+            name,
+            failures: vec![failure],
+            metrics: Metrics::default(),
+            coverage: Coverage::new(),
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl TestCase {
+    /// reads a case from a `.toml`, `.yaml` or `.yml` file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Failure> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path).map_err(|e| Failure::Unreadable(e.to_string()))?;
+        let mut case: Self = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => {
+                serde_yaml::from_str(&s).map_err(|e| Failure::Unreadable(e.to_string()))?
+            }
+            _ => toml::from_str(&s).map_err(|e| Failure::Unreadable(e.to_string()))?,
+        };
+        case.path = path.to_path_buf();
+        Ok(case)
+    }
+
+    pub fn name(&self) -> String {
+        match &self.name {
+            Some(n) => n.clone(),
+            None => stem(&self.path),
+        }
+    }
+
+    fn compiler_config(&self) -> Result<CompilerConfig, Failure> {
+        let mut config = match &self.profile {
+            Some(p) => CompilerConfig::profile(p).ok_or(Failure::UnknownProfile(p.clone()))?,
+            None => CompilerConfig::extended(),
+        };
+        config
+            .hardware_registers
+            .extend(self.input.hardware.keys().cloned());
+        Ok(config)
+    }
+
+    /// compiles every exa, along with its name and the file its coverage is reported under
+    fn compile(&self, compiler: &Compiler) -> Result<Vec<(String, String, Program)>, Failure> {
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+        let mut programs = Vec::with_capacity(self.exas.len());
+        let mut errs = Vec::new();
+        for spec in self.exas.iter() {
+            let (program, file) = match (&spec.source, &spec.file) {
+                (Some(source), None) => (
+                    compiler.compile_program(&source.lines().collect::<Vec<&str>>()),
+                    None,
+                ),
+                (None, Some(file)) => {
+                    let path = dir.join(file);
+                    (compiler.compile_program_file(&path), Some(path))
+                }
+                _ => {
+                    return Err(Failure::Invalid(
+                        "each exa needs either a source or a file".to_string(),
+                    ))
+                }
+            };
+            let program = match program {
+                Ok(p) => p,
+                Err(mut e) => {
+                    errs.append(&mut e);
+                    continue;
+                }
+            };
+            let name = match (&spec.name, &program.metadata.name, &file) {
+                (Some(n), _, _) | (None, Some(n), _) => n.clone(),
+                (None, None, Some(path)) => stem(path),
+                (None, None, None) => {
+                    return Err(Failure::Invalid(
+                        "exas with an inline source need a name".to_string(),
+                    ))
+                }
+            };
+            let coverage_file = match file {
+                Some(path) => path.display().to_string(),
+                None => format!("{}#{}", self.path.display(), name),
+            };
+            programs.push((name, coverage_file, program));
+        }
+        match errs.is_empty() {
+            true => Ok(programs),
+            false => Err(Failure::Compile(errs)),
+        }
+    }
+
+    pub fn run(&self) -> Report {
+        let name = self.name();
+        let config = match self.compiler_config() {
+            Ok(c) => c,
+            Err(e) => return Report::failed(name, e),
+        };
+        let programs = match self.compile(&Compiler::new(config.clone())) {
+            Ok(p) => p,
+            Err(e) => return Report::failed(name, e),
+        };
+
+        let vm_config = VMConfig {
+            profile: true,
+            ..VMConfig::default()
+        };
+        let mut host = Host::from_config(HostConfig::new(
+            name.clone().into_boxed_str().into(),
+            config.into(),
+            vm_config.into(),
+        ));
+        host.capture_output();
+        for f in self.files.iter() {
+            host.insert_file(f.id, file(&f.content));
+        }
+        for (register, values) in self.input.hardware.iter() {
+            host.add_hardware_input(register, registers(values));
+        }
+        for (exa, _, program) in programs.iter() {
+            if let Err(e) = host.add_exa(Exa::from_program(exa, program.clone())) {
+                return Report::failed(name, Failure::Deploy(exa.clone(), e));
+            }
+        }
+
+        let mut m = registers(&self.input.m).into_iter();
+        let mut pending = m.next();
+        while host.exa_count() > 0 && host.metrics().cycles < self.cycles {
+            if let Some(value) = pending.take() {
+                pending = match host.send_m(value) {
+                    Ok(()) => m.next(),
+                    Err(value) => Some(value),
+                };
+            }
+            host.step();
+        }
+
+        let mut failures = Vec::new();
+        match self.expect.exas {
+            Some(expected) if expected != host.exa_count() => {
+                failures.push(Failure::ExasRemaining {
+                    expected,
+                    actual: host.exa_count(),
+                })
+            }
+            None if host.exa_count() > 0 => failures.push(Failure::CycleLimit(self.cycles)),
+            _ => {}
+        }
+        if let Some(expected) = &self.expect.output {
+            let expected = registers(expected);
+            let actual = host.output();
+            if expected != actual {
+                failures.push(Failure::Output { expected, actual });
+            }
+        }
+        for f in self.expect.files.iter() {
+            let expected = registers(&f.content);
+            let actual = host.get_file(f.id).map(|file| file.content().to_vec());
+            if actual.as_ref() != Some(&expected) {
+                failures.push(Failure::File {
+                    id: f.id,
+                    expected,
+                    actual,
+                });
+            }
+        }
+
+        let mut coverage = Coverage::new();
+        let profile = host.profile().unwrap_or_default();
+        for (exa, file, program) in programs.iter() {
+            coverage.add(program, file, Some(&profile.with_clones(exa)));
+        }
+        Report {
+            name,
+            failures,
+            metrics: host.metrics(),
+            coverage,
+        }
+    }
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// runs every `.toml`, `.yaml` and `.yml` test case in `dir`, sorted by file name
+pub fn run_dir<P: AsRef<Path>>(dir: P) -> Result<Vec<Report>, std::io::Error> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_file()
+                && matches!(
+                    p.extension().and_then(|e| e.to_str()),
+                    Some("toml" | "yaml" | "yml")
+                )
+        })
+        .collect();
+    paths.sort();
+    Ok(paths
+        .iter()
+        .map(|path| match TestCase::from_file(path) {
+            Ok(case) => case.run(),
+            Err(e) => Report::failed(stem(path), e),
+        })
+        .collect())
+}
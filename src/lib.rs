@@ -1,27 +1,45 @@
 use std::{
     io::{Read, Write},
     net::ToSocketAddrs,
+    path::Path,
     rc::Rc,
 };
 
 use compiler::{config::Config as CompilerConfig, Compiler};
 use config::{HostConfig, VMConfig};
-use exa::Exa;
+use exa::{Exa, Register, Requirement};
 use file::File;
+use metrics::Metrics;
 use vm::VM;
 
 pub mod compiler;
 pub mod config;
+pub mod coverage;
 pub mod exa;
 pub mod file;
+pub mod harness;
+pub mod lsp;
+pub mod metrics;
 pub mod server;
+pub mod solution;
 pub mod vm;
 
+/// why a host refused to run an exa
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeployError {
+    /// the exa declares a requirement (with `@requires`) the host doesn't provide
+    MissingRequirement(Requirement),
+    /// the exa uses an instruction the host's compiler configuration doesn't allow
+    ForbiddenInstruction(exa::Instruction),
+}
+
 #[derive(Debug)]
 pub struct Host {
     compiler: Compiler,
     vm: VM,
     config: HostConfig,
+    /// total size of the exas deployed with `add_exa`
+    size: usize,
 }
 
 impl Host {
@@ -32,46 +50,157 @@ impl Host {
         let hostname: Rc<Box<str>> = Rc::new(host_name.into());
         Host {
             compiler: exa_compiler,
-            vm: VM::new(hostname.clone(), vm_config.clone()),
-            config: HostConfig {
-                hostname,
-                compiler_config: CompilerConfig::extended().into(),
-                vm_config,
-            },
+            vm: VM::new(
+                hostname.clone(),
+                vm_config.clone(),
+                CompilerConfig::extended().instruction_set(),
+            ),
+            config: HostConfig::new(hostname, CompilerConfig::extended().into(), vm_config),
+            size: 0,
         }
     }
 
     pub fn init() -> Host {
         let config = Self::load_config();
         println!("Initializing host: {}", config.hostname);
+        Self::from_config(config)
+    }
+
+    pub fn from_config(config: HostConfig) -> Host {
         Host {
             compiler: Compiler::new((*config.compiler_config).clone()),
-            vm: VM::new(config.hostname.clone(), config.vm_config.clone()),
+            vm: VM::new(
+                config.hostname.clone(),
+                config.vm_config.clone(),
+                config.compiler_config.instruction_set(),
+            ),
             config,
+            size: 0,
         }
     }
 
+    /// compiles an exa, named by its `@name` directive if it has one, or else by `name`
     pub fn compile_exa(
         &self,
         name: &str,
         instructions: Vec<&str>,
     ) -> Result<Exa, Vec<compiler::Error>> {
-        let instr = self.compiler.compile(&instructions)?;
-        Ok(Exa::new(name, instr))
+        let program = self.compiler.compile_program(&instructions)?;
+        let name = program.metadata.name.clone().unwrap_or(name.to_string());
+        Ok(Exa::from_program(&name, program))
+    }
+
+    /// compiles an exa from a source file, see `Compiler::compile_exa_file`
+    pub fn compile_exa_file<P: AsRef<Path>>(&self, path: P) -> Result<Exa, Vec<compiler::Error>> {
+        self.compiler.compile_exa_file(path)
+    }
+
+    /// compiles every `.exa` file in `dir`, see `Compiler::compile_dir`
+    pub fn compile_exa_dir<P: AsRef<Path>>(
+        &self,
+        dir: P,
+    ) -> Result<Vec<Exa>, Vec<compiler::Error>> {
+        self.compiler.compile_dir(dir)
     }
 
-    pub fn add_exa(&mut self, exa: Exa) {
+    /// deploys an exa compiled on this host, if the host meets its requirements
+    pub fn add_exa(&mut self, exa: Exa) -> Result<(), DeployError> {
+        self.check_requirements(&exa)?;
+        self.size += exa.size;
         self.vm.add_exa(exa);
+        Ok(())
+    }
+
+    /// adds an exa compiled on another host, rejecting it if this host doesn't meet its
+    /// requirements, or it uses an instruction this host's compiler configuration doesn't allow
+    ///
+    /// nothing deploys exas arriving over links yet, so only direct callers get these checks
+    pub fn receive_exa(&mut self, exa: Exa) -> Result<(), DeployError> {
+        self.check_requirements(&exa)?;
+        self.vm
+            .receive_exa(exa)
+            .map_err(DeployError::ForbiddenInstruction)
+    }
+
+    fn check_requirements(&self, exa: &Exa) -> Result<(), DeployError> {
+        for req in exa.metadata.requires.iter() {
+            let met = match req {
+                Requirement::HardwareRegister(r) => self
+                    .compiler
+                    .hardware_registers()
+                    .iter()
+                    .any(|h| h.eq_ignore_ascii_case(r)),
+                Requirement::Instruction(op) => self.compiler.opcodes().contains(op),
+            };
+            if !met {
+                return Err(DeployError::MissingRequirement(req.clone()));
+            }
+        }
+        Ok(())
     }
 
     pub fn add_file(&mut self, file: File) {
         self.vm.add_file(file);
     }
 
+    /// adds a file under the given id, replacing any file that had it
+    pub fn insert_file(&mut self, id: i16, file: File) {
+        self.vm.insert_file(id, file);
+    }
+
+    /// a copy of the file with the given id, `None` if no file has it or an exa is holding it
+    pub fn get_file(&self, id: i16) -> Option<File> {
+        self.vm.get_file(id)
+    }
+
+    /// puts a value in `M` for an exa to read, unless it already holds one
+    pub fn send_m(&mut self, value: Register) -> Result<(), Register> {
+        self.vm.send_m(value)
+    }
+
+    /// queues values to be read from a hardware register, see `VM::add_hardware_input`
+    pub fn add_hardware_input(&mut self, register: &str, values: Vec<Register>) {
+        self.vm.add_hardware_input(register, values);
+    }
+
+    /// keeps `PRNT`ed values for `Host::output` instead of printing them
+    pub fn capture_output(&mut self) {
+        self.vm.capture_output();
+    }
+
+    /// the values `PRNT`ed so far, empty unless output is captured
+    pub fn output(&self) -> Vec<Register> {
+        self.vm.output()
+    }
+
+    /// number of exas still running
+    pub fn exa_count(&self) -> usize {
+        self.vm.exa_count()
+    }
+
     pub fn step(&mut self) {
         self.vm.step();
     }
 
+    /// starts counting how often each instruction executes and blocks, see `VM::profile`
+    pub fn enable_profiling(&mut self) {
+        self.vm.enable_profiling();
+    }
+
+    /// the execution counts collected so far, `None` unless profiling is enabled
+    pub fn profile(&self) -> Option<vm::profile::Profile> {
+        self.vm.profile()
+    }
+
+    /// cycles and activity so far, along with the size of the exas deployed on this host
+    pub fn metrics(&self) -> Metrics {
+        Metrics {
+            cycles: self.vm.cycles(),
+            size: self.size,
+            activity: self.vm.activity(),
+        }
+    }
+
     pub fn connect(&mut self, _address: &(impl ToSocketAddrs + ?Sized)) {
         unimplemented!()
     }
@@ -0,0 +1,443 @@
+//! a small language server for EXA source, speaking JSON-RPC over stdio
+//!
+//! documents are synced in full, and positions are treated as character offsets,
+//! which matches UTF-16 offsets for everything outside the astral planes
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+
+use crate::compiler::{analysis, Compiler, Line, Token, TokenType};
+use crate::exa::OpCode;
+
+const LABEL_OPS: [&str; 5] = ["mark", "jump", "tjmp", "fjmp", "repl"];
+
+pub struct Server {
+    compiler: Compiler,
+    documents: HashMap<String, String>,
+    shutdown: bool,
+}
+
+impl Server {
+    pub fn new(compiler: Compiler) -> Self {
+        Self {
+            compiler,
+            documents: HashMap::new(),
+            shutdown: false,
+        }
+    }
+
+    /// serves requests from `input` until an `exit` notification, or the input closes
+    pub fn run<R, W>(&mut self, mut input: R, mut output: W) -> io::Result<()>
+    where
+        R: BufRead,
+        W: Write,
+    {
+        while let Some(msg) = read_message(&mut input)? {
+            if msg["method"] == "exit" {
+                return Ok(());
+            }
+            for reply in self.handle(&msg) {
+                write_message(&mut output, &reply)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// handles a single message, returning the responses and notifications to send back
+    pub fn handle(&mut self, msg: &Value) -> Vec<Value> {
+        let method = msg["method"].as_str().unwrap_or("");
+        let params = &msg["params"];
+        let id = msg.get("id").cloned();
+
+        if self.shutdown {
+            return match id {
+                Some(id) => vec![json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": -32600, "message": "server is shutting down" },
+                })],
+                None => vec![],
+            };
+        }
+
+        let result = match method {
+            "initialize" => Some(Self::capabilities()),
+            "shutdown" => {
+                self.shutdown = true;
+                Some(Value::Null)
+            }
+            "textDocument/didOpen" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+                let text = params["textDocument"]["text"].as_str().unwrap_or("");
+                self.documents.insert(uri.to_string(), text.to_string());
+                return vec![self.publish_diagnostics(uri)];
+            }
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+                if let Some(change) = params["contentChanges"].as_array().and_then(|c| c.last()) {
+                    let text = change["text"].as_str().unwrap_or("");
+                    self.documents.insert(uri.to_string(), text.to_string());
+                }
+                return vec![self.publish_diagnostics(uri)];
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+                self.documents.remove(uri);
+                return vec![notification(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                )];
+            }
+            "textDocument/hover" => Some(self.hover(params)),
+            "textDocument/definition" => Some(self.definition(params)),
+            "textDocument/completion" => Some(self.completion(params)),
+            "textDocument/rename" => Some(self.rename(params)),
+            _ => None,
+        };
+
+        match (id, result) {
+            (Some(id), Some(result)) => {
+                vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })]
+            }
+            (Some(id), None) => vec![json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": format!("unsupported method '{}'", method) },
+            })],
+            (None, _) => vec![],
+        }
+    }
+
+    fn capabilities() -> Value {
+        json!({
+            "capabilities": {
+                "textDocumentSync": 1,
+                "hoverProvider": true,
+                "definitionProvider": true,
+                "completionProvider": { "triggerCharacters": ["#"] },
+                "renameProvider": true,
+            },
+            "serverInfo": { "name": "exahost-lsp", "version": env!("CARGO_PKG_VERSION") },
+        })
+    }
+
+    fn publish_diagnostics(&self, uri: &str) -> Value {
+        let text = self.documents.get(uri).map(|s| s.as_str()).unwrap_or("");
+        let raw: Vec<&str> = text.lines().collect();
+        let path = uri_to_path(uri);
+        let result = match &path {
+            Some(p) => self.compiler.compile_program_at(p, &raw),
+            None => self.compiler.compile_program(&raw),
+        };
+        // problems in the document itself carry its path too
+        let here = path.map(|p| p.display().to_string());
+        let mut diagnostics = Vec::new();
+        match result {
+            Err(errs) => {
+                for e in errs {
+                    let mut message = format!("{:?}: {}", e.etype, e.context);
+                    for call in e.expansion.iter().rev() {
+                        message.push_str(&format!(
+                            "\nexpanded from `@call {}` on line {}",
+                            call.name,
+                            call.row + 1
+                        ));
+                    }
+                    diagnostics.push(diagnostic(
+                        &raw,
+                        elsewhere(e.file.as_deref(), here.as_deref()),
+                        e.row,
+                        e.col,
+                        None,
+                        1,
+                        message,
+                    ));
+                }
+            }
+            Ok(program) => {
+                for w in analysis::analyze(&program) {
+                    let severity = match w.severity {
+                        analysis::Severity::Warning => 2,
+                        analysis::Severity::Hint => 4,
+                    };
+                    let message = format!("{:?}: {}", w.wtype, w.context);
+                    diagnostics.push(diagnostic(
+                        &raw,
+                        elsewhere(w.file.as_deref(), here.as_deref()),
+                        w.row,
+                        w.col,
+                        Some(w.len),
+                        severity,
+                        message,
+                    ));
+                }
+            }
+        }
+        notification(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        )
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let (lines, row, col) = match self.lex_at(params) {
+            Some(l) => l,
+            None => return Value::Null,
+        };
+        let line = match lines.iter().find(|l| l.row() == row) {
+            Some(l) => l,
+            None => return Value::Null,
+        };
+        let op = match line.first() {
+            Some(Ok(t)) if t.ttype == TokenType::OpCode && contains(t, col) => t,
+            _ => return Value::Null,
+        };
+        match op.content.parse::<OpCode>() {
+            Ok(op) => json!({ "contents": { "kind": "markdown", "value": op.docs() } }),
+            Err(_) => Value::Null,
+        }
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let (lines, row, col) = match self.lex_at(params) {
+            Some(l) => l,
+            None => return Value::Null,
+        };
+        let label = match label_at(&lines, row, col) {
+            Some(l) => l,
+            None => return Value::Null,
+        };
+        let uri = &params["textDocument"]["uri"];
+        for line in lines.iter() {
+            if let Some(t) = label_token(line) {
+                if line_op(line) == "mark" && t.content == label {
+                    return json!({ "uri": uri, "range": token_range(t) });
+                }
+            }
+        }
+        Value::Null
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        let mut items = Vec::new();
+        for op in self.compiler.opcodes() {
+            items.push(json!({
+                "label": op.to_string(),
+                "kind": 14,
+                "documentation": { "kind": "markdown", "value": op.docs() },
+            }));
+        }
+        for reg in ["X", "T", "F", "M"] {
+            items.push(json!({ "label": reg, "kind": 6 }));
+        }
+        for reg in self.compiler.hardware_registers() {
+            items.push(json!({ "label": reg, "kind": 6 }));
+        }
+        if let Some((lines, _, _)) = self.lex_at(params) {
+            let mut labels: Vec<&str> = lines
+                .iter()
+                .filter(|l| line_op(l) == "mark")
+                .filter_map(label_token)
+                .map(|t| t.content.as_str())
+                .collect();
+            labels.sort();
+            labels.dedup();
+            for label in labels {
+                items.push(json!({ "label": label, "kind": 18 }));
+            }
+        }
+        json!(items)
+    }
+
+    fn rename(&self, params: &Value) -> Value {
+        let (lines, row, col) = match self.lex_at(params) {
+            Some(l) => l,
+            None => return Value::Null,
+        };
+        let label = match label_at(&lines, row, col) {
+            Some(l) => l,
+            None => return Value::Null,
+        };
+        let new_name = params["newName"].as_str().unwrap_or(&label);
+        let edits: Vec<Value> = lines
+            .iter()
+            .filter_map(label_token)
+            .filter(|t| t.content == label)
+            .map(|t| json!({ "range": token_range(t), "newText": new_name }))
+            .collect();
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+        json!({ "changes": { uri: edits } })
+    }
+
+    /// lexes the document a request refers to, returning it with the requested position
+    fn lex_at(&self, params: &Value) -> Option<(Vec<Line>, usize, usize)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let text = self.documents.get(uri)?;
+        let raw: Vec<&str> = text.lines().collect();
+        let row = params["position"]["line"].as_u64()? as usize;
+        let col = params["position"]["character"].as_u64()? as usize;
+        Some((self.compiler.lex(&raw), row, col))
+    }
+}
+
+fn line_op(line: &Line) -> String {
+    match line.first() {
+        Some(Ok(t)) => t.content.to_lowercase(),
+        _ => String::new(),
+    }
+}
+
+/// the label argument of a `MARK`, `JUMP`, `TJMP`, `FJMP` or `REPL`
+fn label_token(line: &Line) -> Option<&Token> {
+    if !LABEL_OPS.contains(&&line_op(line)[..]) {
+        return None;
+    }
+    match line.get(1) {
+        Some(Ok(t)) if t.ttype == TokenType::JumpLabel => Some(t),
+        _ => None,
+    }
+}
+
+fn label_at(lines: &[Line], row: usize, col: usize) -> Option<String> {
+    let line = lines.iter().find(|l| l.row() == row)?;
+    let t = label_token(line)?;
+    match contains(t, col) {
+        true => Some(t.content.clone()),
+        false => None,
+    }
+}
+
+fn contains(t: &Token, col: usize) -> bool {
+    (t.col..=t.col + t.content.chars().count()).contains(&col)
+}
+
+fn token_range(t: &Token) -> Value {
+    json!({
+        "start": { "line": t.row, "character": t.col },
+        "end": { "line": t.row, "character": t.col + t.content.chars().count() },
+    })
+}
+
+/// a diagnostic spanning `len` characters at `row`, `col`, or the word there without a `len`,
+/// problems in other files are reported at the top of the document
+fn diagnostic(
+    raw: &[&str],
+    file: Option<&str>,
+    row: usize,
+    col: usize,
+    len: Option<usize>,
+    severity: u8,
+    message: String,
+) -> Value {
+    let (row, col, len, message) = match file {
+        Some(f) => (
+            0,
+            0,
+            0,
+            format!("{}:{}:{}: {}", f, row + 1, col + 1, message),
+        ),
+        None => {
+            let len = len.unwrap_or_else(|| {
+                raw.get(row)
+                    .map(|l| {
+                        l.chars()
+                            .skip(col)
+                            .take_while(|c| !c.is_whitespace())
+                            .count()
+                    })
+                    .unwrap_or(0)
+            });
+            (row, col, usize::max(len, 1), message)
+        }
+    };
+    json!({
+        "range": {
+            "start": { "line": row, "character": col },
+            "end": { "line": row, "character": col + len },
+        },
+        "severity": severity,
+        "source": "exahost",
+        "message": message,
+    })
+}
+
+/// `file`, unless it's the document itself at `here`
+fn elsewhere<'a>(file: Option<&'a str>, here: Option<&str>) -> Option<&'a str> {
+    file.filter(|f| Some(*f) != here)
+}
+
+/// the path of a `file://` URI, `None` for other schemes like `untitled:`
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?;
+    // `file:///C:/...` on Windows
+    let encoded = match encoded.as_bytes().get(2) {
+        Some(b':') => &encoded[1..],
+        _ => encoded,
+    };
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut x = 0;
+    while x < bytes.len() {
+        let escaped = bytes
+            .get(x + 1..x + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[x], escaped) {
+            (b'%', Some(b)) => {
+                decoded.push(b);
+                x += 3;
+            }
+            (b, _) => {
+                decoded.push(b);
+                x += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+/// reads a `Content-Length` framed message, `None` once the input is closed
+fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Value>> {
+    let mut len = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(l) = header.strip_prefix("Content-Length:") {
+            len = l.trim().parse::<usize>().ok();
+        }
+    }
+    let len = match len {
+        Some(l) => l,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "missing Content-Length",
+            ))
+        }
+    };
+    let mut body = vec![0u8; len];
+    input.read_exact(&mut body)?;
+    match serde_json::from_slice(&body) {
+        Ok(v) => Ok(Some(v)),
+        Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+    }
+}
+
+fn write_message<W: Write>(output: &mut W, msg: &Value) -> io::Result<()> {
+    let body = msg.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
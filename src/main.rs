@@ -1,7 +1,24 @@
+use std::path::Path;
+use std::process::ExitCode;
+
+use exahost::coverage::Coverage;
 use exahost::file::File;
+use exahost::harness::run_dir;
+use exahost::metrics::Run;
 use exahost::Host;
 
-fn main() {
+const TEST_USAGE: &str = "usage: exahost test [--lcov <file>] [--save-runs <dir>] DIR
+
+runs every .toml, .yaml and .yml test case in DIR
+  --lcov         write the coverage of all cases to a file, in lcov format
+  --save-runs    save the metrics of each case to <dir>/NAME.json, for exascore";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|a| a == "test") {
+        return test(&args[1..]);
+    }
+
     let mut rhizome = Host::default();
     rhizome.save_config().unwrap();
 
@@ -72,10 +89,10 @@ fn main() {
         }
     }
 
-    let xa = rhizome.compile_exa("XA", switch).unwrap();
-    let xb = rhizome.compile_exa("XB", reader).unwrap();
+    let _xa = rhizome.compile_exa("XA", switch).unwrap();
+    let _xb = rhizome.compile_exa("XB", reader).unwrap();
 
-    let xc = rhizome.compile_exa("XC", vec!["host x", "prnt x"]).unwrap();
+    let _xc = rhizome.compile_exa("XC", vec!["host x", "prnt x"]).unwrap();
 
     let fi = rhizome.compile_exa("FI", fibonacci).unwrap();
 
@@ -86,9 +103,105 @@ fn main() {
     // rhizome.add_exa(xa);
     // rhizome.add_exa(xb);
     // rhizome.add_exa(xc);
-    rhizome.add_exa(fi);
+    if let Err(e) = rhizome.add_exa(fi) {
+        eprintln!("exahost: couldn't deploy FI: {:?}", e);
+        return ExitCode::FAILURE;
+    }
 
     for _ in 0..70 {
         rhizome.step();
     }
+    ExitCode::SUCCESS
+}
+
+fn test(args: &[String]) -> ExitCode {
+    let mut lcov = None;
+    let mut save_runs = None;
+    let mut dir = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--lcov" => match args.next() {
+                Some(p) => lcov = Some(p),
+                None => {
+                    eprintln!("{}", TEST_USAGE);
+                    return ExitCode::from(2);
+                }
+            },
+            "--save-runs" => match args.next() {
+                Some(d) => save_runs = Some(d),
+                None => {
+                    eprintln!("{}", TEST_USAGE);
+                    return ExitCode::from(2);
+                }
+            },
+            "-h" | "--help" => {
+                println!("{}", TEST_USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ if dir.is_none() => dir = Some(arg),
+            _ => {
+                eprintln!("{}", TEST_USAGE);
+                return ExitCode::from(2);
+            }
+        }
+    }
+    let dir = match dir {
+        Some(d) => d,
+        None => {
+            eprintln!("{}", TEST_USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let reports = match run_dir(dir) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("exahost: {}: {}", dir, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut coverage = Coverage::new();
+    let mut failed = 0;
+    for report in reports.iter() {
+        coverage.merge(&report.coverage);
+        if report.passed() {
+            let m = report.metrics;
+            println!(
+                "ok     {} ({} cycles, size {}, activity {})",
+                report.name, m.cycles, m.size, m.activity
+            );
+            continue;
+        }
+        failed += 1;
+        println!("FAILED {}", report.name);
+        for f in report.failures.iter() {
+            println!("  {}", f.to_string().replace('\n', "\n  "));
+        }
+    }
+    println!("\n{} passed, {} failed", reports.len() - failed, failed);
+
+    if let Some(path) = lcov {
+        if let Err(e) = coverage.save(path) {
+            eprintln!("exahost: {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    }
+    if let Some(runs) = save_runs {
+        if let Err(e) = std::fs::create_dir_all(runs) {
+            eprintln!("exahost: {}: {}", runs, e);
+            return ExitCode::FAILURE;
+        }
+        for report in reports.iter() {
+            let path = Path::new(runs).join(format!("{}.json", report.name));
+            if let Err(e) = Run::new(&report.name, report.metrics).save(&path) {
+                eprintln!("exahost: {}: {}", path.display(), e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    match failed {
+        0 => ExitCode::SUCCESS,
+        _ => ExitCode::FAILURE,
+    }
 }
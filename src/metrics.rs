@@ -0,0 +1,104 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// how a run scores, measured the way EXAPUNKS measures solutions
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Metrics {
+    /// steps taken while there were exas to run
+    pub cycles: usize,
+    /// total size of the exas deployed, see `Program::size`
+    pub size: usize,
+    /// number of `LINK`s and `KILL`s executed
+    pub activity: usize,
+}
+
+impl Metrics {
+    pub const NAMES: [&'static str; 3] = ["cycles", "size", "activity"];
+
+    /// the metrics in the order of `Metrics::NAMES`
+    pub fn values(&self) -> [usize; 3] {
+        [self.cycles, self.size, self.activity]
+    }
+}
+
+/// the metrics of a run, saved under a name to be compared with other runs later
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Run {
+    pub name: String,
+    pub metrics: Metrics,
+}
+
+impl Run {
+    pub fn new(name: &str, metrics: Metrics) -> Self {
+        Self {
+            name: name.to_string(),
+            metrics,
+        }
+    }
+
+    /// saves the run as JSON
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), std::io::Error> {
+        let s = serde_json::to_string_pretty(self).unwrap();
+        std::fs::write(path, s)
+    }
+
+    /// reads a run saved with `Run::save`, `None` if it's missing or invalid
+    pub fn load<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let s = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&s).ok()
+    }
+}
+
+const BAR_WIDTH: usize = 40;
+
+/// compares runs, as a table with the best score of each metric marked with `*`,
+/// followed by a histogram of each metric
+pub fn report(runs: &[Run]) -> String {
+    let name_width = runs
+        .iter()
+        .map(|r| r.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("run".len());
+
+    let mut out = format!("{:<name_width$}", "run");
+    for title in Metrics::NAMES {
+        out.push_str(&format!(" {:>10}", title));
+    }
+    out.push('\n');
+    for run in runs {
+        out.push_str(&format!("{:<name_width$}", run.name));
+        for (i, value) in run.metrics.values().into_iter().enumerate() {
+            let best = runs.iter().all(|r| r.metrics.values()[i] >= value);
+            let cell = format!("{}{}", value, if best { "*" } else { " " });
+            out.push_str(&format!(" {:>10}", cell));
+        }
+        out.push('\n');
+    }
+
+    for (i, title) in Metrics::NAMES.iter().enumerate() {
+        out.push_str(&format!("\n{}\n", title));
+        let max = runs
+            .iter()
+            .map(|r| r.metrics.values()[i])
+            .max()
+            .unwrap_or(0);
+        let mut sorted: Vec<&Run> = runs.iter().collect();
+        sorted.sort_by_key(|r| r.metrics.values()[i]);
+        for run in sorted {
+            let value = run.metrics.values()[i];
+            let len = match max {
+                0 => 0,
+                max => (value * BAR_WIDTH).div_ceil(max),
+            };
+            out.push_str(&format!(
+                "  {:<name_width$} {:<BAR_WIDTH$} {}\n",
+                run.name,
+                "#".repeat(len),
+                value
+            ));
+        }
+    }
+    out
+}
@@ -62,6 +62,7 @@ enum ConnectionError {
 
 struct Link {
     input_recv: Receiver<Message>,
+    #[allow(dead_code)]
     output_send: Sender<Message>,
     stream: BufStream<TcpStream>,
     peer_addr: SocketAddr,
@@ -167,6 +168,7 @@ impl Link {
 #[derive(Debug)]
 struct LinkHandle {
     sender: Sender<Message>,
+    #[allow(dead_code)]
     reciever: Receiver<Message>,
 }
 
@@ -182,6 +184,7 @@ impl LinkHandle {
         }
     }
 
+    #[allow(dead_code)]
     async fn send_async(&self, message: Message) -> Result<(), ()> {
         match self.sender.send_async(message).await {
             Ok(_) => Ok(()),
@@ -189,6 +192,7 @@ impl LinkHandle {
         }
     }
 
+    #[allow(dead_code)]
     fn recv(&self) -> Result<Message, ()> {
         match self.reciever.recv() {
             Ok(m) => Ok(m),
@@ -196,6 +200,7 @@ impl LinkHandle {
         }
     }
 
+    #[allow(dead_code)]
     async fn recv_async(&self) -> Result<Message, ()> {
         match self.reciever.recv_async().await {
             Ok(m) => Ok(m),
@@ -224,6 +229,7 @@ impl LinkManager {
         }
     }
 
+    #[allow(clippy::result_unit_err)]
     pub fn send_exa(&self, link: i16, exa: Exa) -> Result<(), ()> {
         let lhs = self.link_handles.lock().unwrap();
         match lhs.get(&link) {
@@ -235,7 +241,7 @@ impl LinkManager {
 
     pub fn recieve_exas(&self) -> Option<Vec<Exa>> {
         let mut exa_q = self.exa_queue.lock().unwrap();
-        if exa_q.len() == 0 {
+        if exa_q.is_empty() {
             return None;
         }
         Some(exa_q.drain(..).collect())
@@ -277,5 +283,6 @@ impl LinkManager {
         }
     }
 
+    #[allow(dead_code)]
     async fn collect_incoming() {}
 }
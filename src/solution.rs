@@ -0,0 +1,358 @@
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::compiler::{Compiler, Error};
+use crate::exa::Exa;
+
+pub use crate::exa::MemoryMode;
+
+/// version number every `.solution` file starts with
+const VERSION: i32 = 1007;
+/// byte every exa in a `.solution` file starts with
+const EXA_MARKER: u8 = 10;
+/// the game's exa sprites are 10x10 pixels
+const SPRITE_SIZE: usize = 100;
+
+/// why a `.solution` file couldn't be read
+#[derive(Debug)]
+pub enum SolutionError {
+    Io(std::io::Error),
+    UnsupportedVersion(i32),
+    /// the file ends in the middle of a value
+    UnexpectedEof,
+    InvalidString,
+    /// an exa doesn't start with the expected marker byte
+    InvalidExaMarker(u8),
+    InvalidMode(u8),
+    /// bytes left over after the last exa
+    TrailingData,
+}
+
+impl From<std::io::Error> for SolutionError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// the score the game recorded the last time the solution was run successfully
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Score {
+    pub cycles: i32,
+    pub size: i32,
+    pub activity: i32,
+}
+
+/// an exa as the game stores it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolutionExa {
+    pub name: String,
+    /// source code, with the game's `\n` line endings
+    pub source: String,
+    /// how the game's editor shows the exa, kept as is
+    pub editor_view: u8,
+    pub mode: MemoryMode,
+    /// pixels of the exa's sprite, row by row
+    pub sprite: [bool; SPRITE_SIZE],
+}
+
+impl SolutionExa {
+    pub fn new(name: &str, source: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            source: source_lines(source).join("\n"),
+            editor_view: 0,
+            mode: MemoryMode::default(),
+            sprite: [false; SPRITE_SIZE],
+        }
+    }
+
+    /// reads an exa written with `to_source`, a `@mode` directive sets the mode
+    /// and is left out of the source, since the game doesn't know it
+    pub fn from_source(name: &str, source: &str) -> Self {
+        let mut mode = MemoryMode::default();
+        let mut lines = Vec::new();
+        for line in source_lines(source) {
+            let lower = line.to_lowercase();
+            let directive = match lower.split_whitespace().collect::<Vec<_>>()[..] {
+                ["@mode", m] => m.parse().ok(),
+                _ => None,
+            };
+            match directive {
+                Some(m) => mode = m,
+                None => lines.push(line),
+            }
+        }
+        Self {
+            mode,
+            ..Self::new(name, &lines.join("\n"))
+        }
+    }
+
+    /// the exa as a source file, starting with `@mode local` if it runs in local mode
+    pub fn to_source(&self) -> String {
+        match self.mode {
+            MemoryMode::Global => format!("{}\n", self.source),
+            MemoryMode::Local => format!("@mode local\n{}\n", self.source),
+        }
+    }
+
+    /// the name of the file to save the exa's source to, `NAME.exa`, or `None` if the name
+    /// could point outside the directory it's saved in, as names come from untrusted files
+    pub fn file_name(&self) -> Option<String> {
+        let separator = |c: char| matches!(c, '/' | '\\' | ':' | '\0');
+        match self.name.is_empty() || self.name.contains("..") || self.name.contains(separator) {
+            true => None,
+            false => Some(format!("{}.exa", self.name)),
+        }
+    }
+
+    /// the source code, split into lines ready for `Compiler::compile_program`
+    pub fn lines(&self) -> Vec<&str> {
+        source_lines(&self.source)
+    }
+
+    /// compiles the exa in its mode, errors carry its name as their file
+    pub fn compile(&self, compiler: &Compiler) -> Result<Exa, Vec<Error>> {
+        match compiler.compile_program(&self.lines()) {
+            Ok(mut program) => {
+                program.metadata.mode = Some(self.mode);
+                Ok(Exa::from_program(&self.name, program))
+            }
+            Err(errs) => {
+                let file: Rc<str> = self.name.clone().into();
+                Err(errs
+                    .into_iter()
+                    .map(|mut e| {
+                        e.file = Some(file.clone());
+                        e
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+/// an EXAPUNKS `.solution` file
+///
+/// all numbers are little endian, strings are UTF-8 prefixed with their length in bytes,
+/// encoded 7 bits at a time like .NET's `BinaryWriter` does
+/// ```text
+/// i32     version, always 1007
+/// string  puzzle id, like PB000
+/// string  solution name
+/// i32     competition wins
+/// i32     redshift program size
+/// i32     number of scores (0 or 3), followed by that many pairs of
+///         i32 kind (0 cycles, 1 size, 2 activity) and i32 value
+/// i32     number of exas, followed by that many of
+///         u8 10, string name, string source, u8 editor view, u8 mode (0 global, 1 local),
+///         100 u8 sprite pixels
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    pub puzzle: String,
+    pub name: String,
+    pub competition_wins: i32,
+    pub redshift_size: i32,
+    /// `None` if the solution was never completed
+    pub score: Option<Score>,
+    pub exas: Vec<SolutionExa>,
+}
+
+impl Solution {
+    pub fn new(puzzle: &str, name: &str, exas: Vec<SolutionExa>) -> Self {
+        Self {
+            puzzle: puzzle.to_string(),
+            name: name.to_string(),
+            competition_wins: 0,
+            redshift_size: 0,
+            score: None,
+            exas,
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SolutionError> {
+        let mut r = Reader { bytes, pos: 0 };
+        let version = r.i32()?;
+        if version != VERSION {
+            return Err(SolutionError::UnsupportedVersion(version));
+        }
+        let puzzle = r.string()?;
+        let name = r.string()?;
+        let competition_wins = r.i32()?;
+        let redshift_size = r.i32()?;
+
+        let mut score = None;
+        let score_count = r.i32()?;
+        if score_count > 0 {
+            let mut s = Score {
+                cycles: 0,
+                size: 0,
+                activity: 0,
+            };
+            for _ in 0..score_count {
+                let kind = r.i32()?;
+                let value = r.i32()?;
+                match kind {
+                    0 => s.cycles = value,
+                    1 => s.size = value,
+                    2 => s.activity = value,
+                    // scores the game added later, not needed here
+                    _ => {}
+                }
+            }
+            score = Some(s);
+        }
+
+        let exa_count = r.i32()?;
+        let mut exas = Vec::with_capacity(exa_count.max(0) as usize);
+        for _ in 0..exa_count {
+            let marker = r.u8()?;
+            if marker != EXA_MARKER {
+                return Err(SolutionError::InvalidExaMarker(marker));
+            }
+            let name = r.string()?;
+            let source = r.string()?;
+            let editor_view = r.u8()?;
+            let mode = match r.u8()? {
+                0 => MemoryMode::Global,
+                1 => MemoryMode::Local,
+                m => return Err(SolutionError::InvalidMode(m)),
+            };
+            let mut sprite = [false; SPRITE_SIZE];
+            for pixel in sprite.iter_mut() {
+                *pixel = r.u8()? != 0;
+            }
+            exas.push(SolutionExa {
+                name,
+                source,
+                editor_view,
+                mode,
+                sprite,
+            });
+        }
+        if r.pos != bytes.len() {
+            return Err(SolutionError::TrailingData);
+        }
+
+        Ok(Self {
+            puzzle,
+            name,
+            competition_wins,
+            redshift_size,
+            score,
+            exas,
+        })
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, SolutionError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend(VERSION.to_le_bytes());
+        write_string(&mut out, &self.puzzle);
+        write_string(&mut out, &self.name);
+        out.extend(self.competition_wins.to_le_bytes());
+        out.extend(self.redshift_size.to_le_bytes());
+        match self.score {
+            Some(s) => {
+                out.extend(3i32.to_le_bytes());
+                for (kind, value) in [s.cycles, s.size, s.activity].into_iter().enumerate() {
+                    out.extend((kind as i32).to_le_bytes());
+                    out.extend(value.to_le_bytes());
+                }
+            }
+            None => out.extend(0i32.to_le_bytes()),
+        }
+        out.extend((self.exas.len() as i32).to_le_bytes());
+        for exa in self.exas.iter() {
+            out.push(EXA_MARKER);
+            write_string(&mut out, &exa.name);
+            write_string(&mut out, &exa.source);
+            out.push(exa.editor_view);
+            out.push(match exa.mode {
+                MemoryMode::Global => 0,
+                MemoryMode::Local => 1,
+            });
+            out.extend(exa.sprite.iter().map(|p| *p as u8));
+        }
+        out
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), std::io::Error> {
+        std::fs::write(path, self.to_bytes())
+    }
+
+    /// compiles every exa in the solution, collecting the errors of all of them
+    pub fn compile(&self, compiler: &Compiler) -> Result<Vec<Exa>, Vec<Error>> {
+        let mut exas = Vec::with_capacity(self.exas.len());
+        let mut errs = Vec::new();
+        for exa in self.exas.iter() {
+            match exa.compile(compiler) {
+                Ok(e) => exas.push(e),
+                Err(mut e) => errs.append(&mut e),
+            }
+        }
+        match errs.is_empty() {
+            true => Ok(exas),
+            false => Err(errs),
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], SolutionError> {
+        if self.bytes.len() - self.pos < n {
+            return Err(SolutionError::UnexpectedEof);
+        }
+        self.pos += n;
+        Ok(&self.bytes[self.pos - n..self.pos])
+    }
+
+    fn u8(&mut self) -> Result<u8, SolutionError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i32(&mut self) -> Result<i32, SolutionError> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, SolutionError> {
+        let mut len = 0usize;
+        let mut shift = 0;
+        loop {
+            let b = self.u8()?;
+            len |= ((b & 0x7f) as usize) << shift;
+            if b & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+            if shift > 28 {
+                return Err(SolutionError::InvalidString);
+            }
+        }
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| SolutionError::InvalidString)
+    }
+}
+
+fn write_string(out: &mut Vec<u8>, s: &str) {
+    let mut len = s.len();
+    while len >= 0x80 {
+        out.push((len as u8 & 0x7f) | 0x80);
+        len >>= 7;
+    }
+    out.push(len as u8);
+    out.extend(s.as_bytes());
+}
+
+/// the game saves `\n` line endings, but files passed around may have picked up `\r\n`
+fn source_lines(source: &str) -> Vec<&str> {
+    source.lines().collect()
+}
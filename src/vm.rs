@@ -1,11 +1,20 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    rc::Rc,
+};
 
 use rand::{rngs::ThreadRng, Rng};
 
+use crate::compiler::config::{Config as CompilerConfig, InstructionSet};
 use crate::config::VMConfig;
-use crate::exa::{Arg, Comp, Exa, OpCode, RegLabel, Register};
+use crate::exa::{Arg, Comp, Exa, Instruction, OpCode, RegLabel, Register};
 use crate::file::File;
 
+pub mod profile;
+
+use profile::{Outcome, Profile};
+
 #[derive(Debug, Clone, Copy)]
 enum ExaResult {
     SideEffect(SideEffect),
@@ -21,7 +30,8 @@ impl ExaResult {
 
 #[derive(Debug, Clone, Copy)]
 enum SideEffect {
-    Repl(u8),
+    Repl(u16),
+    #[allow(dead_code)]
     Link(i16),
     Kill,
     Halt,
@@ -54,17 +64,37 @@ pub struct VM {
 
     files: RefCell<HashMap<i16, File>>,
     hostname: Rc<Box<str>>,
+    #[allow(dead_code)]
     config: Rc<VMConfig>,
+    instruction_set: InstructionSet,
+
+    cycles: usize,
+    activity: usize,
+    profile: RefCell<Option<Profile>>,
+    /// values `PRNT`ed, when they're captured instead of printed
+    output: RefCell<Option<Vec<Register>>>,
+    /// values left to read from each hardware register, by upper case name
+    hardware: RefCell<HashMap<String, VecDeque<Register>>>,
 }
 
 impl VM {
-    pub fn new(hostname: Rc<Box<str>>, config: Rc<VMConfig>) -> Self {
+    pub fn new(
+        hostname: Rc<Box<str>>,
+        config: Rc<VMConfig>,
+        instruction_set: InstructionSet,
+    ) -> Self {
         Self {
             exas: HashMap::with_capacity(config.max_exas),
             reg_m: RefCell::new(None),
             rng: RefCell::new(rand::thread_rng()),
             files: RefCell::new(HashMap::with_capacity(config.max_files)),
             hostname,
+            instruction_set,
+            cycles: 0,
+            activity: 0,
+            profile: RefCell::new(config.profile.then(Profile::default)),
+            output: RefCell::new(None),
+            hardware: RefCell::new(HashMap::new()),
             config,
         }
     }
@@ -73,10 +103,67 @@ impl VM {
         if self.exas.is_empty() {
             return;
         }
+        self.cycles += 1;
         let results = self.exec_all();
         self.apply_side_effects(results);
     }
 
+    /// number of steps taken while there were exas to run
+    pub fn cycles(&self) -> usize {
+        self.cycles
+    }
+
+    /// number of `LINK`s and `KILL`s executed
+    pub fn activity(&self) -> usize {
+        self.activity
+    }
+
+    /// starts counting how often each instruction executes and blocks, see `VM::profile`
+    pub fn enable_profiling(&mut self) {
+        self.profile.get_mut().get_or_insert_with(Profile::default);
+    }
+
+    /// the execution counts collected so far, `None` unless profiling is enabled
+    pub fn profile(&self) -> Option<Profile> {
+        self.profile.borrow().clone()
+    }
+
+    /// keeps `PRNT`ed values for `VM::output` instead of printing them
+    pub fn capture_output(&mut self) {
+        self.output.get_mut().get_or_insert_with(Vec::new);
+    }
+
+    /// the values `PRNT`ed so far, empty unless output is captured
+    pub fn output(&self) -> Vec<Register> {
+        self.output.borrow().clone().unwrap_or_default()
+    }
+
+    /// puts a value in `M` for an exa to read, unless it already holds one
+    pub fn send_m(&mut self, value: Register) -> Result<(), Register> {
+        let reg_m = self.reg_m.get_mut();
+        match reg_m.is_some() {
+            true => Err(value),
+            false => {
+                *reg_m = Some(value);
+                Ok(())
+            }
+        }
+    }
+
+    /// queues values to be read from a hardware register, like `#NERV`,
+    /// reading it blocks once they run out
+    pub fn add_hardware_input(&mut self, register: &str, values: Vec<Register>) {
+        self.hardware
+            .get_mut()
+            .entry(register.to_uppercase())
+            .or_default()
+            .extend(values);
+    }
+
+    pub fn exa_count(&self) -> usize {
+        self.exas.len()
+    }
+
     pub fn add_exa(&mut self, exa: Exa) {
         self.exas.insert(
             match self.exas.keys().max() {
@@ -87,6 +174,30 @@ impl VM {
         );
     }
 
+    /// adds an exa compiled elsewhere, if every instruction is allowed on this host,
+    /// otherwise returns the first one that isn't
+    pub fn receive_exa(&mut self, exa: Exa) -> Result<(), Instruction> {
+        if let Some(instr) = exa
+            .instr_list
+            .iter()
+            .find(|i| !self.instruction_set.permits(i))
+        {
+            return Err(instr.clone());
+        }
+        self.add_exa(exa);
+        Ok(())
+    }
+
+    /// adds a file under the given id, replacing any file that had it
+    pub fn insert_file(&mut self, id: i16, f: File) {
+        self.files.get_mut().insert(id, f);
+    }
+
+    /// a copy of the file with the given id, `None` if no file has it or an exa is holding it
+    pub fn get_file(&self, id: i16) -> Option<File> {
+        self.files.borrow().get(&id).cloned()
+    }
+
     pub fn add_file(&mut self, f: File) {
         let max = {
             match self.files.borrow().keys().max() {
@@ -117,6 +228,7 @@ impl VM {
                         self.exas.insert(key, val);
                     }
                     SideEffect::Kill => {
+                        self.activity += 1;
                         for k2 in self.exas.keys() {
                             if k2 != &k {
                                 self.exas.remove(&k);
@@ -125,6 +237,7 @@ impl VM {
                         }
                     }
                     SideEffect::Link(_) => {
+                        self.activity += 1;
                         self.exas.remove(&k);
                     }
                     SideEffect::Halt => {
@@ -144,7 +257,7 @@ impl VM {
         }
     }
 
-    fn generate_clone(&mut self, k: &usize, j: u8) -> (usize, RefCell<Exa>) {
+    fn generate_clone(&mut self, k: &usize, j: u16) -> (usize, RefCell<Exa>) {
         let mut clone = self.exas.get(k).unwrap().borrow().clone();
         clone.instr_ptr = j + 1;
         clone.name.push_str(&format!(":{}", clone.repl_counter));
@@ -155,7 +268,7 @@ impl VM {
     fn exec(&self, exa: &RefCell<Exa>) -> Result<(), ExaResult> {
         let instr = {
             let eb = exa.borrow();
-            if eb.instr_ptr as usize == eb.instr_list.len() {
+            if eb.instr_ptr as usize >= eb.instr_list.len() {
                 return Err(ExaResult::Error(RuntimeError::OutOfInstructions));
             }
             eb.instr_list[eb.instr_ptr as usize].clone()
@@ -166,6 +279,8 @@ impl VM {
             exa.borrow_mut().instr_ptr += 1;
             return self.exec(exa);
         }
+        let ptr = exa.borrow().instr_ptr as usize;
+        let conditional = matches!(instr.0, OpCode::Tjmp | OpCode::Fjmp);
 
         let res: Result<(), ExaResult> = match instr.0 {
             OpCode::Copy => self.copy(exa, instr.two_args()),
@@ -210,6 +325,15 @@ impl VM {
             OpCode::Prnt => self.prnt(exa, instr.one_arg()),
         };
 
+        if let Some(profile) = self.profile.borrow_mut().as_mut() {
+            let outcome = match res {
+                Err(ExaResult::Block(Block::Send | Block::Recv)) => Outcome::Blocked,
+                Err(ExaResult::Block(Block::Jump)) if conditional => Outcome::Taken,
+                _ => Outcome::Executed,
+            };
+            profile.record(&exa.borrow().name, ptr, outcome);
+        }
+
         if let Err(e) = res {
             if e.is_block() {
                 return Err(e);
@@ -222,7 +346,11 @@ impl VM {
 
 impl Default for VM {
     fn default() -> Self {
-        Self::new(Rc::new("Rhizome".into()), Rc::new(VMConfig::default()))
+        Self::new(
+            Rc::new("Rhizome".into()),
+            Rc::new(VMConfig::default()),
+            CompilerConfig::default().instruction_set(),
+        )
     }
 }
 
@@ -430,7 +558,7 @@ impl VM {
 
     fn jump(exa: &RefCell<Exa>, target: Arg) -> Result<(), ExaResult> {
         exa.borrow_mut().instr_ptr = match target.jump_index() {
-            Ok(n) => n as u8,
+            Ok(n) => n,
             Err(_) => return Err(ExaResult::Error(RuntimeError::InvalidArgument)),
         };
         Err(ExaResult::Block(Block::Jump))
@@ -524,8 +652,11 @@ impl VM {
     }
 
     fn prnt(&self, exa: &RefCell<Exa>, target: Arg) -> Result<(), ExaResult> {
-        let name = { exa.borrow().name.clone() };
-        println!("{}> {}", name, self.get_value(exa, target)?);
+        let value = self.get_value(exa, target)?;
+        match self.output.borrow_mut().as_mut() {
+            Some(output) => output.push(value),
+            None => println!("{}> {}", exa.borrow().name, value),
+        }
         Ok(())
     }
 
@@ -557,7 +688,10 @@ impl VM {
                     Some(r) => Ok(r),
                     None => Err(ExaResult::Block(Block::Recv)),
                 },
-                RegLabel::H(_) => Err(ExaResult::Error(RuntimeError::InvalidHWRegAccess)),
+                RegLabel::H(h) => match self.hardware.borrow_mut().get_mut(&h.to_uppercase()) {
+                    Some(values) => values.pop_front().ok_or(ExaResult::Block(Block::Recv)),
+                    None => Err(ExaResult::Error(RuntimeError::InvalidHWRegAccess)),
+                },
             },
             _ => Err(ExaResult::Error(RuntimeError::InvalidArgument)),
         }
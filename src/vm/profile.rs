@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use crate::compiler::Program;
+
+/// how often each instruction of an exa ran, indexed the same as its instructions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExaProfile {
+    /// times the instruction executed, including the one that errored
+    pub executed: Vec<usize>,
+    /// cycles spent waiting on the instruction to send or receive through `M`
+    pub blocked: Vec<usize>,
+    /// times a `TJMP` or `FJMP` jumped, rather than falling through
+    pub taken: Vec<usize>,
+}
+
+impl ExaProfile {
+    fn count(counts: &mut Vec<usize>, index: usize) {
+        if counts.len() <= index {
+            counts.resize(index + 1, 0);
+        }
+        counts[index] += 1;
+    }
+
+    pub(super) fn record(&mut self, index: usize, outcome: Outcome) {
+        match outcome {
+            Outcome::Executed => Self::count(&mut self.executed, index),
+            Outcome::Blocked => Self::count(&mut self.blocked, index),
+            Outcome::Taken => {
+                Self::count(&mut self.executed, index);
+                Self::count(&mut self.taken, index);
+            }
+        }
+    }
+
+    /// adds the counts of `other` to these
+    pub fn merge(&mut self, other: &ExaProfile) {
+        for (counts, more) in [
+            (&mut self.executed, &other.executed),
+            (&mut self.blocked, &other.blocked),
+            (&mut self.taken, &other.taken),
+        ] {
+            if counts.len() < more.len() {
+                counts.resize(more.len(), 0);
+            }
+            for (c, m) in counts.iter_mut().zip(more.iter()) {
+                *c += m;
+            }
+        }
+    }
+
+    pub fn executed(&self, index: usize) -> usize {
+        self.executed.get(index).copied().unwrap_or(0)
+    }
+
+    pub fn blocked(&self, index: usize) -> usize {
+        self.blocked.get(index).copied().unwrap_or(0)
+    }
+
+    pub fn taken(&self, index: usize) -> usize {
+        self.taken.get(index).copied().unwrap_or(0)
+    }
+
+    /// counts summed up by the source line the instructions were compiled from,
+    /// keyed by file and row, since `@rep`s compile many instructions from one line
+    pub fn by_line(&self, program: &Program) -> BTreeMap<(Option<String>, usize), (usize, usize)> {
+        let mut lines = BTreeMap::new();
+        for (i, location) in program.source_map.iter().enumerate() {
+            let counts = lines
+                .entry((location.file.as_deref().map(String::from), location.row))
+                .or_insert((0, 0));
+            counts.0 += self.executed(i);
+            counts.1 += self.blocked(i);
+        }
+        lines
+    }
+
+    /// annotates the lines of `source` with how often the instructions compiled from them
+    /// executed and blocked, along with a bar showing how hot each line is.
+    /// `file` is what the program's source map calls `source`, `None` if it was passed in directly
+    pub fn annotate(&self, program: &Program, file: Option<&str>, source: &[&str]) -> String {
+        let lines: BTreeMap<usize, (usize, usize)> = self
+            .by_line(program)
+            .into_iter()
+            .filter(|((f, _), _)| f.as_deref() == file)
+            .map(|((_, row), counts)| (row, counts))
+            .collect();
+        let hottest = lines.values().map(|(e, b)| e + b).max().unwrap_or(0);
+
+        let mut out = format!(
+            "{:>8} {:>8} {:<HEAT_WIDTH$} | source\n",
+            "hits", "blocked", "heat"
+        );
+        for (row, text) in source.iter().enumerate() {
+            let (hits, blocked) = match lines.get(&row) {
+                Some(counts) => *counts,
+                None => {
+                    out.push_str(&format!(
+                        "{:>8} {:>8} {:HEAT_WIDTH$} | {}\n",
+                        "", "", "", text
+                    ));
+                    continue;
+                }
+            };
+            let heat = match hottest {
+                0 => 0,
+                hottest => ((hits + blocked) * HEAT_WIDTH).div_ceil(hottest),
+            };
+            out.push_str(&format!(
+                "{:>8} {:>8} {:<HEAT_WIDTH$} | {}\n",
+                hits,
+                blocked,
+                "#".repeat(heat),
+                text
+            ));
+        }
+        out
+    }
+}
+
+const HEAT_WIDTH: usize = 10;
+
+/// what happened when an instruction ran for a cycle
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Outcome {
+    Executed,
+    /// waited to send or receive through `M`
+    Blocked,
+    /// a `TJMP` or `FJMP` jumped
+    Taken,
+}
+
+/// execution counts of every exa that ran while profiling, by name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    pub exas: BTreeMap<String, ExaProfile>,
+}
+
+impl Profile {
+    pub fn exa(&self, name: &str) -> Option<&ExaProfile> {
+        self.exas.get(name)
+    }
+
+    /// the counts of the exa called `name` together with those of its `REPL` clones,
+    /// which run the same program
+    pub fn with_clones(&self, name: &str) -> ExaProfile {
+        let mut merged = ExaProfile::default();
+        for (exa, p) in self.exas.iter() {
+            if exa == name || exa.strip_prefix(name).is_some_and(|c| c.starts_with(':')) {
+                merged.merge(p);
+            }
+        }
+        merged
+    }
+
+    pub(super) fn record(&mut self, exa: &str, index: usize, outcome: Outcome) {
+        match self.exas.get_mut(exa) {
+            Some(p) => p.record(index, outcome),
+            None => {
+                let mut p = ExaProfile::default();
+                p.record(index, outcome);
+                self.exas.insert(exa.to_string(), p);
+            }
+        }
+    }
+}
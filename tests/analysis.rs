@@ -0,0 +1,127 @@
+mod common;
+
+use common::compiler;
+use exahost::compiler::analysis::{self, WarningType};
+
+/// the type and row of each warning the lints report for `source`
+fn lints(source: &[&str]) -> Vec<(WarningType, usize)> {
+    let program = compiler().compile_program(source).unwrap();
+    analysis::analyze(&program)
+        .into_iter()
+        .map(|w| (w.wtype, w.row))
+        .collect()
+}
+
+#[test]
+fn code_after_halt_is_unreachable() {
+    assert_eq!(
+        lints(&["copy 1 x", "halt", "addi x 1 x", "addi x 1 x"]),
+        [(WarningType::UnreachableCode, 2)]
+    );
+    // only code no path reaches
+    assert_eq!(
+        lints(&["mark l", "addi x 1 x", "test x > 3", "fjmp l", "halt"]),
+        []
+    );
+}
+
+#[test]
+fn code_reached_through_repl_is_not_unreachable() {
+    assert_eq!(lints(&["repl a", "halt", "mark a", "kill", "halt"]), []);
+}
+
+#[test]
+fn falling_off_the_end_is_reported_once() {
+    assert_eq!(
+        lints(&["copy 1 x", "addi x 1 x"]),
+        [(WarningType::FallsOffEnd, 1)]
+    );
+    assert_eq!(lints(&["copy 1 x", "halt"]), []);
+    // a loop never reaches the end
+    assert_eq!(lints(&["mark l", "addi x 1 x", "jump l"]), []);
+}
+
+#[test]
+fn files_have_to_be_held() {
+    assert_eq!(lints(&["copy f x", "halt"]), [(WarningType::NoFileHeld, 0)]);
+    assert_eq!(lints(&["seek 1", "halt"]), [(WarningType::NoFileHeld, 0)]);
+    assert_eq!(lints(&["grab 200", "copy f x", "seek 1", "halt"]), []);
+    assert_eq!(lints(&["make", "copy 1 f", "halt"]), []);
+    // one path without a file is enough
+    assert_eq!(
+        lints(&["tjmp l", "grab 200", "mark l", "copy f x", "halt"]),
+        [(WarningType::NoFileHeld, 3)]
+    );
+    assert_eq!(
+        lints(&["grab 200", "drop", "copy f x", "halt"]),
+        [(WarningType::NoFileHeld, 2)]
+    );
+}
+
+#[test]
+fn unused_labels_are_hints() {
+    let program = compiler().compile_program(&["mark l", "halt"]).unwrap();
+    let warnings = analysis::analyze(&program);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].wtype, WarningType::UnusedLabel);
+    assert_eq!(warnings[0].severity, analysis::Severity::Hint);
+    assert_eq!(warnings[0].context, "l");
+    assert_eq!(lints(&["jump l", "mark l", "halt"]), []);
+    assert_eq!(lints(&["repl l", "halt", "mark l", "halt"]), []);
+}
+
+#[test]
+fn dividing_by_zero_is_reported() {
+    assert_eq!(
+        lints(&["divi x 0 x", "modi x 0 x", "halt"]),
+        [
+            (WarningType::DivisionByZero, 0),
+            (WarningType::DivisionByZero, 1)
+        ]
+    );
+    assert_eq!(lints(&["divi x 2 x", "divi 0 x x", "halt"]), []);
+}
+
+#[test]
+fn arithmetic_on_keywords_is_reported() {
+    assert_eq!(
+        lints(&["copy 'ABC' x", "addi x 1 x", "halt"]),
+        [(WarningType::KeywordArithmetic, 1)]
+    );
+    // once it's a number again, or might be one
+    assert_eq!(
+        lints(&["copy 'ABC' x", "copy 2 x", "addi x 1 x", "halt"]),
+        []
+    );
+    assert_eq!(
+        lints(&["tjmp l", "copy 'ABC' x", "mark l", "addi x 1 x", "halt"]),
+        []
+    );
+}
+
+#[test]
+fn comparing_numbers_with_keywords_is_reported() {
+    assert_eq!(
+        lints(&["copy 1 x", "test x = 'A'", "halt"]),
+        [(WarningType::MismatchedComparison, 1)]
+    );
+    assert_eq!(lints(&["copy 'A' x", "test x = 'A'", "halt"]), []);
+    assert_eq!(lints(&["copy 1 x", "test x < 2", "halt"]), []);
+}
+
+#[test]
+fn warnings_span_their_instruction() {
+    let source = ["copy 'ABC' x", "  addi x 1 x   ", "mark unused", "halt"];
+    let program = compiler().compile_program(&source).unwrap();
+    let spans: Vec<_> = analysis::analyze(&program)
+        .into_iter()
+        .map(|w| (w.row, w.col, w.len))
+        .collect();
+    assert_eq!(spans, [(1, 2, 10), (2, 0, 11)]);
+    // with the label it was written with, not the index it became
+    let program = compiler()
+        .compile_program(&["mark loop", "tjmp loop"])
+        .unwrap();
+    let warning = &analysis::analyze(&program)[0];
+    assert_eq!((warning.row, warning.col, warning.len), (1, 0, 9));
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use common::compiler;
+use exahost::compiler::cfg::{Cfg, Edge, EdgeKind};
+use exahost::compiler::Program;
+
+fn program(source: &[&str]) -> Program {
+    compiler().compile_program(source).unwrap()
+}
+
+/// the instruction range of each block
+fn blocks(cfg: &Cfg) -> Vec<(usize, usize)> {
+    cfg.blocks.iter().map(|b| (b.start, b.end)).collect()
+}
+
+fn edge(from: usize, to: usize, kind: EdgeKind) -> Edge {
+    Edge { from, to, kind }
+}
+
+#[test]
+fn blocks_split_at_jumps_and_labels() {
+    let program = program(&[
+        "copy 1 x",
+        "mark a",
+        "addi x 1 x",
+        "test x > 3",
+        "fjmp a",
+        "copy 0 x",
+        "halt",
+    ]);
+    let cfg = Cfg::new(&program);
+    assert_eq!(blocks(&cfg), [(0, 1), (1, 4), (4, 6)]);
+    assert_eq!(cfg.blocks[1].labels, ["a"]);
+    assert_eq!(cfg.entry, Some(0));
+    assert_eq!(cfg.block_of(2), 1);
+    assert!(cfg.blocks.iter().all(|b| !b.falls_off_end));
+}
+
+#[test]
+fn conditional_jumps_are_taken_or_fall_through() {
+    for op in ["tjmp", "fjmp"] {
+        let jump = format!("{} a", op);
+        let cfg = Cfg::new(&program(&[&jump, "copy 1 x", "mark a", "halt"]));
+        assert_eq!(blocks(&cfg), [(0, 1), (1, 2), (2, 3)]);
+        assert_eq!(
+            cfg.edges,
+            [
+                edge(0, 2, EdgeKind::Taken),
+                edge(0, 1, EdgeKind::Fallthrough),
+                edge(1, 2, EdgeKind::Fallthrough),
+            ]
+        );
+    }
+}
+
+#[test]
+fn jumps_never_fall_through() {
+    let cfg = Cfg::new(&program(&["jump a", "copy 1 x", "mark a", "halt"]));
+    assert_eq!(
+        cfg.edges,
+        [
+            edge(0, 2, EdgeKind::Taken),
+            edge(1, 2, EdgeKind::Fallthrough)
+        ]
+    );
+    assert_eq!(cfg.reachable(), [true, false, true]);
+}
+
+#[test]
+fn repl_reaches_its_target_and_the_next_instruction() {
+    let cfg = Cfg::new(&program(&["repl a", "halt", "mark a", "kill"]));
+    assert_eq!(
+        cfg.edges,
+        [
+            edge(0, 2, EdgeKind::Repl),
+            edge(0, 1, EdgeKind::Fallthrough)
+        ]
+    );
+    assert_eq!(cfg.reachable(), [true, true, true]);
+    assert!(cfg.blocks[2].falls_off_end);
+}
+
+#[test]
+fn dot_lists_blocks_and_edges() {
+    let program = program(&["mark a", "addi x 1 x", "tjmp a"]);
+    let dot = Cfg::new(&program).to_dot(&program);
+    let lines: Vec<&str> = dot.lines().collect();
+    assert_eq!(lines[0], "digraph exa {");
+    assert!(lines.contains(&"    b0 [label=\"MARK a\\l0: ADDI X 1 X\\l1: TJMP 0\\l\"];"));
+    assert!(lines.contains(&"    start -> b0;"));
+    assert!(lines.contains(&"    b0 -> b0 [label=\"taken\", style=bold];"));
+    assert!(lines.contains(&"    b0 -> end [style=dotted];"));
+    assert_eq!(lines.last(), Some(&"}"));
+}
+
+#[test]
+fn json_has_blocks_with_their_instructions() {
+    let program = program(&["repl a", "halt", "mark a", "kill"]);
+    let json = Cfg::new(&program).to_json(&program);
+    assert_eq!(json["entry"], 0);
+    assert_eq!(json["blocks"].as_array().unwrap().len(), 3);
+    let block = &json["blocks"][2];
+    assert_eq!(block["start"], 2);
+    assert_eq!(block["end"], 3);
+    assert_eq!(block["labels"][0], "a");
+    assert_eq!(block["falls_off_end"], true);
+    assert_eq!(block["instructions"][0]["index"], 2);
+    assert_eq!(block["instructions"][0]["instruction"], "KILL");
+    assert_eq!(block["instructions"][0]["row"], 3);
+    assert_eq!(json["edges"][0]["from"], 0);
+    assert_eq!(json["edges"][0]["to"], 2);
+    assert_eq!(json["edges"][0]["kind"], "repl");
+}
@@ -0,0 +1,30 @@
+//! helpers shared by the integration tests, each test only uses some of them
+#![allow(dead_code)]
+
+use exahost::compiler::{config::Config, Compiler, Error, ErrorType};
+
+pub fn compiler() -> Compiler {
+    Compiler::new(Config::extended())
+}
+
+/// compiles `source`, which has to compile, back into one line of text per instruction
+pub fn disassemble(source: &[&str]) -> Vec<String> {
+    let compiler = compiler();
+    let program = compiler.compile_program(source).unwrap();
+    compiler.disassemble(&program.instructions)
+}
+
+/// the type of each error compiling `source` reports
+pub fn error_types(source: &[&str]) -> Vec<ErrorType> {
+    compiler()
+        .compile_program(source)
+        .unwrap_err()
+        .into_iter()
+        .map(|e| e.etype)
+        .collect()
+}
+
+/// the errors compiling `source` reports
+pub fn errors(source: &[&str]) -> Vec<Error> {
+    compiler().compile_program(source).unwrap_err()
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use common::{disassemble, error_types, errors};
+use exahost::compiler::ErrorType;
+
+#[test]
+fn constants_are_substituted() {
+    let instructions = disassemble(&[
+        "@const LIMIT 5",
+        "@const GREETING 'hi'",
+        "test x < LIMIT",
+        "prnt GREETING",
+    ]);
+    assert_eq!(instructions, ["TEST X < 5", "PRNT 'hi'"]);
+}
+
+#[test]
+fn redefining_a_constant_is_an_error() {
+    assert_eq!(
+        error_types(&["@const LIMIT 5", "@const LIMIT 6"]),
+        [ErrorType::DuplicateConstant]
+    );
+}
+
+#[test]
+fn constants_can_be_used_in_expressions() {
+    assert_eq!(
+        disassemble(&["@const LIMIT 5", "prnt @{LIMIT * 2}"]),
+        ["PRNT 10"]
+    );
+}
+
+#[test]
+fn undefined_constants_in_expressions_are_errors() {
+    assert_eq!(
+        error_types(&["prnt @{MISSING}"]),
+        [ErrorType::UndefinedConstant]
+    );
+}
+
+#[test]
+fn constants_used_as_rep_counts_are_checked() {
+    let errs = errors(&["@const N -2", "noop", "@rep N", "noop", "@end"]);
+    assert_eq!(errs.len(), 1);
+    assert_eq!(errs[0].etype, ErrorType::NumberOutOfRange);
+    // reported where the constant is used, not where it's defined
+    assert_eq!((errs[0].row, errs[0].col), (2, 5));
+
+    assert_eq!(
+        error_types(&["@const N 'two'", "@rep N", "noop", "@end"]),
+        [ErrorType::InvalidNumber]
+    );
+    assert_eq!(
+        disassemble(&["@const N 2", "@rep N", "noop", "@end"]),
+        ["NOOP", "NOOP"]
+    );
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use common::{compiler, disassemble, errors};
+use exahost::compiler::ErrorType;
+
+#[test]
+fn calls_substitute_parameters() {
+    let instructions = disassemble(&[
+        "@def BUMP R N",
+        "addi $R $N $R",
+        "@end",
+        "@call BUMP x 5",
+        "@call BUMP t -1",
+    ]);
+    assert_eq!(instructions, ["ADDI X 5 X", "ADDI T -1 T"]);
+}
+
+#[test]
+fn expanded_code_maps_back_to_the_definition() {
+    let program = compiler()
+        .compile_program(&["@def BUMP", "addi x 1 x", "@end", "noop", "@call BUMP"])
+        .unwrap();
+    let rows: Vec<usize> = program.source_map.iter().map(|l| l.row).collect();
+    assert_eq!(rows, [3, 1]);
+}
+
+#[test]
+fn errors_point_to_the_definition_and_the_call() {
+    let errs = errors(&["@def BAD", "bogus x", "@end", "noop", "@call BAD"]);
+    assert_eq!(errs.len(), 1);
+    assert_eq!(errs[0].etype, ErrorType::UnknownInstruction);
+    assert_eq!(errs[0].row, 1);
+    assert_eq!(errs[0].expansion.len(), 1);
+    assert_eq!(errs[0].expansion[0].name, "BAD");
+    assert_eq!(errs[0].expansion[0].row, 4);
+}
+
+#[test]
+fn recursive_calls_are_errors() {
+    let errs = errors(&[
+        "@def A", "@call B", "@end", "@def B", "@call A", "@end", "@call A",
+    ]);
+    assert_eq!(errs[0].etype, ErrorType::RecursiveMacro);
+    let names: Vec<&str> = errs[0].expansion.iter().map(|c| &c.name[..]).collect();
+    assert_eq!(names, ["A", "B"]);
+}
+
+#[test]
+fn bad_calls_are_errors() {
+    assert_eq!(errors(&["@call NOPE"])[0].etype, ErrorType::UndefinedMacro);
+    assert_eq!(
+        errors(&["@def TWO P Q", "noop", "@end", "@call TWO 1"])[0].etype,
+        ErrorType::MacroArgMismatch
+    );
+    assert_eq!(
+        errors(&["@def A", "@end", "@def A", "@end"])[0].etype,
+        ErrorType::DuplicateMacro
+    );
+}
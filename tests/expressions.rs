@@ -0,0 +1,33 @@
+mod common;
+
+use common::{disassemble, error_types, errors};
+use exahost::compiler::ErrorType;
+
+#[test]
+fn expressions_use_the_rep_counters() {
+    assert_eq!(
+        disassemble(&["@rep 3", "copy @{2 - I * 2} x", "@end"]),
+        ["COPY 2 X", "COPY 0 X", "COPY -2 X"]
+    );
+}
+
+#[test]
+fn negative_counts_point_at_the_expression() {
+    let errs = errors(&["@rep @{0-1}", "noop", "@end"]);
+    assert_eq!(errs.len(), 1);
+    assert_eq!(errs[0].etype, ErrorType::NumberOutOfRange);
+    assert_eq!((errs[0].row, errs[0].col), (0, 5));
+    assert_eq!(errs[0].context, "@{0-1}");
+}
+
+#[test]
+fn errors_in_a_rep_are_reported_once() {
+    assert_eq!(
+        error_types(&["@rep 3", "copy @{1/0} x", "@end"]),
+        [ErrorType::InvalidExpression]
+    );
+    assert_eq!(
+        error_types(&["@rep 2", "@rep -2", "noop", "@end", "@end"]),
+        [ErrorType::NumberOutOfRange]
+    );
+}
@@ -0,0 +1,22 @@
+mod common;
+
+use common::compiler;
+
+#[test]
+fn canonicalizes_keyword_escapes() {
+    let c = compiler();
+    let source = [r"prnt 'a\sb'", r"copy 'it\'s \\ fine' x"];
+    let formatted = c.format(&source);
+    assert_eq!(formatted, "PRNT 'a b'\nCOPY 'it\\'s \\\\ fine' X\n");
+
+    let lines: Vec<&str> = formatted.lines().collect();
+    assert_eq!(
+        c.compile_program(&lines).unwrap().instructions,
+        c.compile_program(&source).unwrap().instructions
+    );
+}
+
+#[test]
+fn keeps_malformed_keywords() {
+    assert_eq!(compiler().format(&[r"prnt 'a\qb'"]), "PRNT 'a\\qb'\n");
+}
@@ -0,0 +1,52 @@
+use exahost::harness::{run_dir, Failure};
+use exahost::metrics::Run;
+
+#[test]
+fn cases_pass() {
+    let reports = run_dir("tests/cases").unwrap();
+    assert_eq!(reports.len(), 3);
+    for report in reports.iter() {
+        let failures: Vec<String> = report.failures.iter().map(|f| f.to_string()).collect();
+        assert!(report.passed(), "{}: {:#?}", report.name, failures);
+    }
+}
+
+#[test]
+fn failures_are_reported() {
+    let reports = run_dir("tests/cases/failing").unwrap();
+    let failures = &reports[0].failures;
+    assert!(matches!(failures[0], Failure::CycleLimit(10)));
+    assert!(matches!(failures[1], Failure::Output { .. }));
+    assert!(matches!(failures[2], Failure::File { actual: None, .. }));
+}
+
+#[test]
+fn collects_metrics_and_coverage() {
+    let reports = run_dir("tests/cases").unwrap();
+    let countdown = reports.iter().find(|r| r.name == "countdown").unwrap();
+    assert_eq!(countdown.metrics.size, 9);
+    assert!(countdown.metrics.cycles > 0);
+    let lcov = countdown.coverage.to_lcov();
+    assert!(lcov.contains("SF:tests/cases/exas/countdown.exa"));
+    // TJMP LOOP jumps twice and falls through once
+    assert!(lcov.contains("BRDA:9,6,0,2\nBRDA:9,6,1,1"));
+}
+
+#[test]
+fn saves_runs() {
+    let dir = std::env::temp_dir().join(format!("exahost-runs-{}", std::process::id()));
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_exahost"))
+        .args(["test", "--save-runs"])
+        .arg(&dir)
+        .arg("tests/cases")
+        .stdout(std::process::Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let run = Run::load(dir.join("countdown.json")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let reports = run_dir("tests/cases").unwrap();
+    let countdown = reports.iter().find(|r| r.name == "countdown").unwrap();
+    assert_eq!(run.name, "countdown");
+    assert_eq!(run.metrics, countdown.metrics);
+}
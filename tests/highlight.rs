@@ -0,0 +1,96 @@
+mod common;
+
+use common::compiler;
+use exahost::compiler::{ErrorType, SpanKind, TokenType};
+
+/// the position, length and kind of each span of `source`
+fn spans(source: &[&str]) -> Vec<(usize, usize, usize, SpanKind)> {
+    compiler()
+        .highlight(source)
+        .into_iter()
+        .map(|s| (s.row, s.col, s.len, s.kind))
+        .collect()
+}
+
+fn token(row: usize, col: usize, len: usize, ttype: TokenType) -> (usize, usize, usize, SpanKind) {
+    (row, col, len, SpanKind::Token(ttype))
+}
+
+fn error(row: usize, col: usize, len: usize, etype: ErrorType) -> (usize, usize, usize, SpanKind) {
+    (row, col, len, SpanKind::Error(etype))
+}
+
+#[test]
+fn classifies_each_token() {
+    assert_eq!(
+        spans(&["COPY 'ABC' X", "TEST X = 1", "MARK L", "JUMP L"]),
+        [
+            token(0, 0, 4, TokenType::OpCode),
+            token(0, 5, 5, TokenType::Keyword),
+            token(0, 11, 1, TokenType::RegisterLabel),
+            token(1, 0, 4, TokenType::OpCode),
+            token(1, 5, 1, TokenType::RegisterLabel),
+            token(1, 7, 1, TokenType::Comparison),
+            token(1, 9, 1, TokenType::Number),
+            token(2, 0, 4, TokenType::OpCode),
+            token(2, 5, 1, TokenType::JumpLabel),
+            token(3, 0, 4, TokenType::OpCode),
+            token(3, 5, 1, TokenType::JumpLabel),
+        ]
+    );
+}
+
+#[test]
+fn classifies_comments() {
+    assert_eq!(
+        spans(&["NOTE whole line", "HALT ;; trailing"]),
+        [
+            token(0, 0, 15, TokenType::Comment),
+            token(1, 0, 4, TokenType::OpCode),
+            token(1, 5, 11, TokenType::Comment),
+        ]
+    );
+}
+
+#[test]
+fn counts_columns_in_characters() {
+    assert_eq!(
+        spans(&["COPY 'é' X", "COPY 'ÄÖ' 1"]),
+        [
+            token(0, 0, 4, TokenType::OpCode),
+            token(0, 5, 3, TokenType::Keyword),
+            token(0, 9, 1, TokenType::RegisterLabel),
+            token(1, 0, 4, TokenType::OpCode),
+            token(1, 5, 4, TokenType::Keyword),
+            token(1, 10, 1, TokenType::Number),
+            error(1, 10, 1, ErrorType::ArgTypeMismatch),
+        ]
+    );
+}
+
+#[test]
+fn marks_error_ranges_over_their_tokens() {
+    assert_eq!(
+        spans(&["BOGUS 1", "COPY 1", "JUMP NOWHERE"]),
+        [
+            token(0, 0, 5, TokenType::OpCode),
+            error(0, 0, 5, ErrorType::UnknownInstruction),
+            token(0, 6, 1, TokenType::Number),
+            token(1, 0, 4, TokenType::OpCode),
+            token(1, 5, 1, TokenType::Number),
+            // the missing argument, just past the end of the line
+            error(1, 6, 1, ErrorType::SigLenMismatch),
+            token(2, 0, 4, TokenType::OpCode),
+            token(2, 5, 7, TokenType::JumpLabel),
+            error(2, 5, 7, ErrorType::UndefinedLabel),
+        ]
+    );
+}
+
+#[test]
+fn leaves_out_errors_in_other_files() {
+    let source = ["@include \"tests/fixtures/include/main.exa\""];
+    assert!(compiler().compile_program(&source).is_err());
+    let kinds: Vec<SpanKind> = spans(&source).into_iter().map(|s| s.3).collect();
+    assert!(kinds.iter().all(|k| !matches!(k, SpanKind::Error(_))));
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::{compiler, errors};
+use exahost::compiler::ErrorType;
+
+const DIR: &str = "tests/fixtures/include";
+
+/// included files are canonicalized, so only the end of their path is checked
+fn is_from(file: &Option<std::rc::Rc<str>>, path: &str) -> bool {
+    file.as_deref()
+        .is_some_and(|f| f.ends_with(&format!("include/{}", path)))
+}
+
+#[test]
+fn includes_files_relative_to_the_includer() {
+    let program = compiler()
+        .compile_program_file(format!("{}/ok.exa", DIR))
+        .unwrap();
+    assert_eq!(program.len(), 2);
+    assert!(is_from(&program.source_map[0].file, "lib/helper.exa"));
+    assert!(is_from(&program.source_map[1].file, "ok.exa"));
+}
+
+#[test]
+fn substitution_errors_carry_the_included_file() {
+    let errs = compiler()
+        .compile_program_file(format!("{}/main.exa", DIR))
+        .unwrap_err();
+    assert_eq!(errs.len(), 1);
+    assert_eq!(errs[0].etype, ErrorType::ArgTypeMismatch);
+    assert!(is_from(&errs[0].file, "lib/bad_substitution.exa"));
+    assert_eq!(errs[0].row, 2);
+}
+
+#[test]
+fn reports_include_cycles() {
+    let errs = compiler()
+        .compile_program_file(format!("{}/cycle_a.exa", DIR))
+        .unwrap_err();
+    assert_eq!(errs.len(), 1);
+    assert_eq!(errs[0].etype, ErrorType::IncludeCycle);
+    assert!(is_from(&errs[0].file, "cycle_b.exa"));
+}
+
+#[test]
+fn reports_missing_includes() {
+    let errs = errors(&["@include \"nowhere.exa\""]);
+    assert_eq!(errs[0].etype, ErrorType::IncludeNotFound);
+    assert_eq!(errs[0].context, "nowhere.exa");
+}
+
+#[test]
+fn reports_unreadable_root_files() {
+    let path = format!("{}/missing.exa", DIR);
+    let errs = compiler().compile_program_file(&path).unwrap_err();
+    assert_eq!(errs.len(), 1);
+    assert_eq!(errs[0].etype, ErrorType::UnreadableSource);
+    assert!(is_from(&errs[0].file, "missing.exa"));
+    let io = std::fs::read_to_string(&path).unwrap_err();
+    assert_eq!(errs[0].context, io.to_string());
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use common::{compiler, disassemble, error_types};
+use exahost::compiler::ErrorType;
+use exahost::exa::Arg;
+
+fn keyword(source: &str) -> String {
+    let program = compiler().compile_program(&[source]).unwrap();
+    match &program.instructions[0].1 {
+        Some(Arg::Keyword(k)) => k.to_string(),
+        arg => panic!("expected a keyword, found {:?}", arg),
+    }
+}
+
+#[test]
+fn unescapes_keywords() {
+    assert_eq!(keyword(r"prnt 'it\'s'"), "it's");
+    assert_eq!(keyword(r"prnt 'a\\b'"), r"a\b");
+    assert_eq!(keyword(r"prnt '\s'"), " ");
+}
+
+#[test]
+fn rejects_bad_keywords() {
+    assert_eq!(error_types(&[r"prnt 'a\qb'"]), [ErrorType::InvalidEscape]);
+    assert_eq!(
+        error_types(&["prnt 'open"]),
+        [ErrorType::UnterminatedKeyword]
+    );
+    assert_eq!(
+        error_types(&[&format!("prnt '{}'", "x".repeat(257))]),
+        [ErrorType::KeywordTooLong]
+    );
+    assert!(compiler()
+        .compile_program(&[&format!("prnt '{}'", "x".repeat(256))])
+        .is_ok());
+}
+
+#[test]
+fn disassembly_round_trips() {
+    let source = [r"prnt 'it\'s a\\b'", "copy 'two words' x"];
+    let text = disassemble(&source);
+    assert_eq!(text, [r"PRNT 'it\'s a\\b'", "COPY 'two words' X"]);
+    let text: Vec<&str> = text.iter().map(String::as_str).collect();
+    let c = compiler();
+    assert_eq!(
+        c.compile_program(&text).unwrap().instructions,
+        c.compile_program(&source).unwrap().instructions
+    );
+}
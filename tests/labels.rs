@@ -0,0 +1,46 @@
+mod common;
+
+use common::compiler;
+use exahost::exa::Arg;
+
+/// the instruction each jump of `source` targets
+fn targets(source: &[&str]) -> Vec<u16> {
+    compiler()
+        .compile_program(source)
+        .unwrap()
+        .instructions
+        .iter()
+        .filter_map(|i| match i.1 {
+            Some(Arg::JumpIndex(j)) => Some(j),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn local_labels_are_scoped_to_their_iteration() {
+    assert_eq!(
+        targets(&["@rep 3", "mark .L", "addi x 1 x", "jump .L", "@end"]),
+        [0, 2, 4]
+    );
+}
+
+#[test]
+fn next_and_previous_iterations_wrap_around() {
+    let next = ["@rep 3", "mark .L", "jump .L+", "@end"];
+    assert_eq!(targets(&next), [1, 2, 0]);
+    let previous = ["@rep 3", "mark .L", "jump .L-", "@end"];
+    assert_eq!(targets(&previous), [2, 0, 1]);
+    let single = ["@rep 1", "mark .L", "tjmp .L+", "fjmp .L-", "@end"];
+    assert_eq!(targets(&single), [0, 0]);
+}
+
+#[test]
+fn blocks_reusing_a_local_label_do_not_clash() {
+    assert_eq!(
+        targets(&[
+            "@rep 2", "mark .L", "jump .L", "@end", "@rep 2", "mark .L", "jump .L+", "@end",
+        ]),
+        [0, 1, 3, 2]
+    );
+}
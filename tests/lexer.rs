@@ -0,0 +1,36 @@
+mod common;
+
+use common::{compiler, disassemble, errors};
+use exahost::compiler::ErrorType;
+
+#[test]
+fn tabs_separate_words() {
+    assert_eq!(disassemble(&["\tcopy\t1\tx"]), ["COPY 1 X"]);
+    let program = compiler().compile_program(&["\tcopy\t1\tx"]).unwrap();
+    assert_eq!(program.source_map[0].col, 1);
+}
+
+#[test]
+fn keywords_keep_unicode_and_spaces() {
+    assert_eq!(
+        disassemble(&["  prnt 'héllo wörld'", "prnt '日本' ;; 註釋"]),
+        ["PRNT 'héllo wörld'", "PRNT '日本'"]
+    );
+}
+
+#[test]
+fn columns_count_characters_not_bytes() {
+    let errs = errors(&["prnt 'é' bogus"]);
+    assert_eq!(errs[0].etype, ErrorType::SigLenMismatch);
+    assert_eq!(errs[0].col, 14);
+
+    let errs = errors(&["\tprnt\t'ü'\tzz"]);
+    assert_eq!(errs[0].col, 12);
+}
+
+#[test]
+fn unicode_is_not_an_instruction() {
+    let errs = errors(&["écopy 1 x"]);
+    assert_eq!(errs[0].etype, ErrorType::UnknownInstruction);
+    assert_eq!(errs[0].context, "écopy");
+}
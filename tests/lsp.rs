@@ -0,0 +1,139 @@
+mod common;
+
+use common::compiler;
+use exahost::lsp::Server;
+use serde_json::{json, Value};
+
+const SOURCE: &str = "MARK LOOP\nADDI X 1 X\nJUMP LOOP\n";
+
+fn uri(path: &str) -> String {
+    format!("file://{}/{}", env!("CARGO_MANIFEST_DIR"), path)
+}
+
+/// a server with `text` open at `uri`, and the diagnostics opening it published
+fn open(uri: &str, text: &str) -> (Server, Value) {
+    let mut server = Server::new(compiler());
+    let mut replies = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": { "textDocument": { "uri": uri, "text": text } },
+    }));
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0]["method"], "textDocument/publishDiagnostics");
+    let diagnostics = replies.remove(0)["params"]["diagnostics"].take();
+    (server, diagnostics)
+}
+
+/// the result of a request at `row`, `col` of the document at `uri`
+fn request(server: &mut Server, method: &str, uri: &str, row: usize, col: usize) -> Value {
+    let mut replies = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": {
+            "textDocument": { "uri": uri },
+            "position": { "line": row, "character": col },
+            "newName": "AGAIN",
+        },
+    }));
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0]["id"], 1);
+    replies.remove(0)["result"].take()
+}
+
+fn range(row: usize, start: usize, end: usize) -> Value {
+    json!({
+        "start": { "line": row, "character": start },
+        "end": { "line": row, "character": end },
+    })
+}
+
+#[test]
+fn diagnostics_cover_errors_and_warnings() {
+    let (_, diagnostics) = open("untitled:a", "COPY 1 X\nBOGUS\nHALT\n");
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(diagnostics[0]["range"], range(1, 0, 5));
+    assert_eq!(diagnostics[0]["severity"], 1);
+
+    let (_, diagnostics) = open("untitled:a", "HALT\nADDI X 1 X\n");
+    assert_eq!(diagnostics[0]["range"], range(1, 0, 10));
+    assert_eq!(diagnostics[0]["severity"], 2);
+}
+
+#[test]
+fn diagnostics_resolve_includes_next_to_the_document() {
+    let path = "tests/fixtures/include/ok.exa";
+    let text = std::fs::read_to_string(path).unwrap();
+    let (_, diagnostics) = open(&uri(path), &text);
+    // the include is found, only the jump to the end is worth a warning
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(diagnostics[0]["message"], "FallsOffEnd: JUMP 2");
+    assert_eq!(diagnostics[0]["range"], range(1, 0, 9));
+
+    // errors in the included file are reported at the top, with its path
+    let path = "tests/fixtures/include/main.exa";
+    let text = std::fs::read_to_string(path).unwrap();
+    let (_, diagnostics) = open(&uri(path), &text);
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(diagnostics[0]["range"], range(0, 0, 0));
+    let message = diagnostics[0]["message"].as_str().unwrap();
+    assert!(message.contains("lib/bad_substitution.exa:3:"));
+}
+
+#[test]
+fn diagnostics_decode_the_uri() {
+    let (_, diagnostics) = open(
+        &uri("tests/fixtures/include/%6Fk.exa"),
+        "@include \"lib/helper.exa\"\nHALT\n",
+    );
+    assert_eq!(diagnostics, json!([]));
+}
+
+#[test]
+fn hover_documents_the_opcode() {
+    let (mut server, _) = open("untitled:a", SOURCE);
+    let hover = request(&mut server, "textDocument/hover", "untitled:a", 1, 2);
+    assert_eq!(hover["contents"]["kind"], "markdown");
+    assert!(hover["contents"]["value"]
+        .as_str()
+        .unwrap()
+        .contains("ADDI"));
+    let hover = request(&mut server, "textDocument/hover", "untitled:a", 1, 6);
+    assert_eq!(hover, Value::Null);
+}
+
+#[test]
+fn definition_finds_the_mark() {
+    let (mut server, _) = open("untitled:a", SOURCE);
+    let definition = request(&mut server, "textDocument/definition", "untitled:a", 2, 6);
+    assert_eq!(definition["uri"], "untitled:a");
+    assert_eq!(definition["range"], range(0, 5, 9));
+}
+
+#[test]
+fn completion_offers_opcodes_registers_and_labels() {
+    let (mut server, _) = open("untitled:a", SOURCE);
+    let completion = request(&mut server, "textDocument/completion", "untitled:a", 3, 0);
+    let labels: Vec<&str> = completion
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect();
+    for label in ["ADDI", "JUMP", "X", "T", "F", "M", "LOOP"] {
+        assert!(labels.contains(&label), "{} missing", label);
+    }
+}
+
+#[test]
+fn rename_edits_every_use_of_the_label() {
+    let (mut server, _) = open("untitled:a", SOURCE);
+    let rename = request(&mut server, "textDocument/rename", "untitled:a", 0, 6);
+    assert_eq!(
+        rename["changes"]["untitled:a"],
+        json!([
+            { "range": range(0, 5, 9), "newText": "AGAIN" },
+            { "range": range(2, 5, 9), "newText": "AGAIN" },
+        ])
+    );
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use std::rc::Rc;
+
+use common::{compiler, error_types};
+use exahost::compiler::{config::Config, ErrorType};
+use exahost::config::{HostConfig, VMConfig};
+use exahost::exa::{Exa, OpCode, Requirement};
+use exahost::{DeployError, Host};
+
+/// a host running the game's instruction set, without hardware registers
+fn game_host() -> Host {
+    Host::from_config(HostConfig::new(
+        Rc::new("game".into()),
+        Rc::new(Config::game_faithful()),
+        Rc::new(VMConfig::default()),
+    ))
+}
+
+#[test]
+fn entry_sets_where_execution_starts() {
+    let program = compiler()
+        .compile_program(&["copy 1 x", "mark start", "copy 2 x", "@entry start"])
+        .unwrap();
+    assert_eq!(program.metadata.entry.as_deref(), Some("start"));
+    assert_eq!(program.entry(), 1);
+    assert_eq!(Exa::from_program("XA", program).instr_ptr, 1);
+
+    let program = compiler().compile_program(&["copy 1 x"]).unwrap();
+    assert_eq!(program.entry(), 0);
+}
+
+#[test]
+fn entry_counts_as_a_use_of_its_label() {
+    let program = compiler()
+        .compile_program(&["@entry start", "mark start", "halt"])
+        .unwrap();
+    assert_eq!(program.labels[0].uses, 1);
+}
+
+#[test]
+fn entry_has_to_name_a_label() {
+    assert_eq!(
+        error_types(&["@entry nowhere", "copy 1 x"]),
+        [ErrorType::UndefinedLabel]
+    );
+    assert_eq!(
+        error_types(&["@entry a", "@entry a", "mark a"]),
+        [ErrorType::DuplicateDirective]
+    );
+}
+
+#[test]
+fn requirements_are_parsed() {
+    let program = compiler()
+        .compile_program(&["@requires #nerv prnt", "halt"])
+        .unwrap();
+    assert_eq!(
+        program.metadata.requires,
+        [
+            Requirement::HardwareRegister("#NERV".to_string()),
+            Requirement::Instruction(OpCode::Prnt)
+        ]
+    );
+    assert_eq!(
+        error_types(&["@requires bogus", "halt"]),
+        [ErrorType::InvalidRequirement]
+    );
+}
+
+#[test]
+fn hosts_reject_exas_whose_requirements_they_lack() {
+    let program = compiler()
+        .compile_program(&["@requires #NERV", "halt"])
+        .unwrap();
+    let exa = Exa::from_program("XA", program);
+    let missing = Err(DeployError::MissingRequirement(
+        Requirement::HardwareRegister("#NERV".to_string()),
+    ));
+    let mut host = game_host();
+    assert_eq!(host.add_exa(exa.clone()), missing);
+    assert_eq!(host.receive_exa(exa), missing);
+    assert_eq!(host.exa_count(), 0);
+
+    let program = compiler()
+        .compile_program(&["@requires prnt", "halt"])
+        .unwrap();
+    let exa = Exa::from_program("XA", program);
+    assert_eq!(
+        host.add_exa(exa),
+        Err(DeployError::MissingRequirement(Requirement::Instruction(
+            OpCode::Prnt
+        )))
+    );
+}
+
+#[test]
+fn hosts_accept_exas_whose_requirements_they_meet() {
+    let config = Config {
+        hardware_registers: vec!["#NERV".to_string()],
+        ..Config::game_faithful()
+    };
+    let mut host = Host::from_config(HostConfig::new(
+        Rc::new("nerve".into()),
+        Rc::new(config),
+        Rc::new(VMConfig::default()),
+    ));
+    let program = compiler()
+        .compile_program(&["@requires #nerv", "halt"])
+        .unwrap();
+    let exa = Exa::from_program("XA", program);
+    host.add_exa(exa.clone()).unwrap();
+    host.receive_exa(exa).unwrap();
+    assert_eq!(host.exa_count(), 2);
+}
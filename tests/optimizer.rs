@@ -0,0 +1,85 @@
+use exahost::compiler::{config::Config, Compiler, Program};
+use exahost::exa::Arg;
+
+fn compile(source: &[&str]) -> Program {
+    let config = Config {
+        optimize: true,
+        ..Config::default()
+    };
+    Compiler::new(config).compile_program(source).unwrap()
+}
+
+fn label(program: &Program, name: &str) -> usize {
+    program
+        .labels
+        .iter()
+        .find(|l| l.name == name)
+        .map(|l| l.index)
+        .unwrap()
+}
+
+fn rows(program: &Program) -> Vec<usize> {
+    program.source_map.iter().map(|l| l.row).collect()
+}
+
+#[test]
+fn retargets_jumps_across_removed_instructions() {
+    let program = compile(&[
+        "tjmp end", "noop", "copy 2 x", "noop", "mark end", "copy 1 x",
+    ]);
+    assert_eq!(program.len(), 3);
+    assert_eq!(program.instructions[0].1, Some(Arg::JumpIndex(2)));
+    assert_eq!(label(&program, "end"), 2);
+}
+
+#[test]
+fn moves_labels_off_removed_instructions() {
+    let program = compile(&["copy 1 x", "mark a", "noop", "addi x 1 x", "jump a"]);
+    assert_eq!(program.len(), 3);
+    assert_eq!(label(&program, "a"), 1);
+    assert_eq!(program.instructions[2].1, Some(Arg::JumpIndex(1)));
+}
+
+#[test]
+fn keeps_labels_at_the_end() {
+    let program = compile(&[
+        "copy 1 x", "tjmp end", "noop", "copy 2 x", "noop", "mark end",
+    ]);
+    assert_eq!(program.len(), 3);
+    assert_eq!(label(&program, "end"), 3);
+    assert_eq!(program.instructions[1].1, Some(Arg::JumpIndex(3)));
+}
+
+#[test]
+fn source_map_follows_removals() {
+    let program = compile(&[
+        "noop",
+        "copy 1 x",
+        "copy x x",
+        "noop",
+        "addi x 2 x",
+        "copy t t",
+    ]);
+    assert_eq!(program.source_map.len(), program.len());
+    assert_eq!(rows(&program), [1, 4]);
+    // the game counts the size before optimizing
+    assert_eq!(program.size, 6);
+}
+
+#[test]
+fn leaves_side_effects_alone() {
+    let source = [
+        "copy m m",
+        "copy f f",
+        "copy #nerv #nerv",
+        "addi m 0 x",
+        "muli 1 f x",
+        "subi #nerv 0 #nerv",
+    ];
+    let program = compile(&source);
+    assert_eq!(program.len(), source.len());
+    let unoptimized = Compiler::new(Config::default())
+        .compile_program(&source)
+        .unwrap();
+    assert_eq!(program.instructions, unoptimized.instructions);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use std::rc::Rc;
+
+use common::compiler;
+use exahost::compiler::{config::Config, Program};
+use exahost::config::{HostConfig, VMConfig};
+use exahost::exa::{Exa, Register};
+use exahost::Host;
+
+const LOOP: [&str; 7] = [
+    "copy 1 x",
+    "mark l",
+    "addi x 1 x",
+    "test x < 3",
+    "tjmp l",
+    "copy m x",
+    "halt",
+];
+
+/// a profiling host running `source` as `XA`
+fn profiled(source: &[&str]) -> (Host, Program) {
+    let program = compiler().compile_program(source).unwrap();
+    let mut host = Host::from_config(HostConfig::new(
+        Rc::new("profiled".into()),
+        Rc::new(Config::extended()),
+        Rc::new(VMConfig::default()),
+    ));
+    host.enable_profiling();
+    host.add_exa(Exa::from_program("XA", program.clone()))
+        .unwrap();
+    (host, program)
+}
+
+fn steps(host: &mut Host, cycles: usize) {
+    for _ in 0..cycles {
+        host.step();
+    }
+}
+
+#[test]
+fn counts_executions_and_jumps_taken() {
+    let (mut host, _) = profiled(&LOOP);
+    steps(&mut host, 7);
+    let profile = host.profile().unwrap();
+    let xa = profile.exa("XA").unwrap();
+    assert_eq!(
+        (0..6).map(|i| xa.executed(i)).collect::<Vec<_>>(),
+        [1, 2, 2, 2, 0, 0]
+    );
+    // the loop went around once, then fell through
+    assert_eq!(xa.taken(3), 1);
+    assert_eq!(xa.executed(4), 0);
+}
+
+#[test]
+fn counts_cycles_blocked_receiving() {
+    let (mut host, _) = profiled(&LOOP);
+    steps(&mut host, 12);
+    host.send_m(Register::Number(5)).unwrap();
+    steps(&mut host, 3);
+    let xa = host.profile().unwrap().exa("XA").unwrap().clone();
+    assert_eq!(xa.blocked(4), 5);
+    assert_eq!(xa.executed(4), 1);
+    assert_eq!(xa.executed(5), 1);
+}
+
+#[test]
+fn counts_cycles_blocked_sending() {
+    let (mut host, _) = profiled(&["copy 1 m", "copy 2 m", "halt"]);
+    steps(&mut host, 4);
+    let xa = host.profile().unwrap().exa("XA").unwrap().clone();
+    assert_eq!(xa.executed(0), 1);
+    assert_eq!(xa.blocked(1), 3);
+    assert_eq!(xa.executed(1), 0);
+}
+
+#[test]
+fn annotates_each_line() {
+    let (mut host, program) = profiled(&LOOP);
+    steps(&mut host, 12);
+    host.send_m(Register::Number(5)).unwrap();
+    steps(&mut host, 3);
+    let profile = host.profile().unwrap();
+    let annotated = profile.exa("XA").unwrap().annotate(&program, None, &LOOP);
+    assert_eq!(
+        annotated,
+        "    hits  blocked heat       | source\n\
+         \x20      1        0 ##         | copy 1 x\n\
+         \x20                            | mark l\n\
+         \x20      2        0 ####       | addi x 1 x\n\
+         \x20      2        0 ####       | test x < 3\n\
+         \x20      2        0 ####       | tjmp l\n\
+         \x20      1        5 ########## | copy m x\n\
+         \x20      1        0 ##         | halt\n"
+    );
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use std::rc::Rc;
+
+use common::compiler;
+use exahost::compiler::{config::Config, Compiler, ErrorType};
+use exahost::config::{HostConfig, VMConfig};
+use exahost::exa::{Arg, Exa, Register};
+use exahost::Host;
+
+/// `JUMP`s over `skipped` instructions, then prints `X`
+fn jump_over(skipped: usize) -> Vec<String> {
+    let mut source = vec!["jump end".to_string()];
+    source.extend(std::iter::repeat_n("addi x 1 x".to_string(), skipped));
+    source.extend(["mark end".to_string(), "prnt x".to_string()]);
+    source
+}
+
+fn lines(source: &[String]) -> Vec<&str> {
+    source.iter().map(|l| l.as_str()).collect()
+}
+
+#[test]
+fn jumps_reach_past_255_instructions() {
+    let source = jump_over(300);
+    let program = compiler().compile_program(&lines(&source)).unwrap();
+    assert_eq!(program.len(), 302);
+    assert_eq!(program.instructions[0].1, Some(Arg::JumpIndex(301)));
+
+    let mut host = Host::from_config(HostConfig::new(
+        Rc::new("long".into()),
+        Rc::new(Config::extended()),
+        Rc::new(VMConfig::default()),
+    ));
+    host.capture_output();
+    host.add_exa(Exa::from_program("long", program)).unwrap();
+    while host.exa_count() > 0 {
+        host.step();
+    }
+    assert_eq!(host.output(), [Register::Number(0)]);
+}
+
+#[test]
+fn programs_over_the_limit_are_too_large() {
+    let compiler = Compiler::new(Config {
+        max_program_size: 10,
+        ..Config::extended()
+    });
+    let source = jump_over(8);
+    assert!(compiler.compile_program(&lines(&source)).is_ok());
+    let source = jump_over(9);
+    let errs = compiler.compile_program(&lines(&source)).unwrap_err();
+    assert_eq!(errs.len(), 1);
+    assert_eq!(errs[0].etype, ErrorType::ProgramTooLarge);
+    // at the first instruction over the limit, the `PRNT`, as the `MARK` isn't one
+    assert_eq!(errs[0].row, 11);
+}
+
+#[test]
+fn jump_indices_survive_the_bytecode() {
+    let source = jump_over(300);
+    let program = compiler().compile_program(&lines(&source)).unwrap();
+    let exa = Exa::from_program("long", program);
+    let bytes = bincode::serialize(&exa).unwrap();
+    let read: Exa = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(read.instr_list[0].1, Some(Arg::JumpIndex(301)));
+    assert_eq!(read, exa);
+}
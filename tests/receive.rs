@@ -0,0 +1,101 @@
+use std::rc::Rc;
+
+use exahost::compiler::{config::Config, Compiler};
+use exahost::config::{HostConfig, VMConfig};
+use exahost::exa::{Exa, OpCode};
+use exahost::{DeployError, Host};
+
+fn sandboxed() -> Host {
+    Host::from_config(HostConfig::new(
+        Rc::new("sandbox".into()),
+        Rc::new(Config::sandboxed()),
+        Rc::new(VMConfig::default()),
+    ))
+}
+
+/// an exa compiled on a host that allows everything
+fn foreign(name: &str, source: &[&str]) -> Exa {
+    let program = Compiler::new(Config::extended())
+        .compile_program(source)
+        .unwrap();
+    Exa::from_program(name, program)
+}
+
+#[test]
+fn rejects_forbidden_instructions() {
+    let mut host = sandboxed();
+    for (source, op) in [("kill", OpCode::Kill), ("link 800", OpCode::Link)] {
+        let exa = foreign("intruder", &["copy 1 x", source]);
+        match host.receive_exa(exa) {
+            Err(DeployError::ForbiddenInstruction(instr)) => assert_eq!(instr.0, op),
+            r => panic!("{} was not rejected: {:?}", source, r),
+        }
+    }
+    assert_eq!(host.exa_count(), 0);
+}
+
+#[test]
+fn accepts_allowed_instructions() {
+    let mut host = sandboxed();
+    host.receive_exa(foreign("visitor", &["copy 1 x", "addi x 1 x"]))
+        .unwrap();
+    assert_eq!(host.exa_count(), 1);
+}
+
+/// the configuration the TOML of a host describes, with the usual `[vm_config]`
+fn host_config(toml: &str) -> Result<HostConfig, toml::de::Error> {
+    let vm = "[vm_config]\nmax_exas = 9\nmax_files = 9\n";
+    toml::from_str(&format!("hostname = \"sandbox\"\n{}\n{}", toml, vm))
+}
+
+#[test]
+fn hosts_can_start_from_a_profile() {
+    let config = host_config("profile = \"sandboxed\"").unwrap();
+    assert_eq!(config.profile.as_deref(), Some("sandboxed"));
+    let mut host = Host::from_config(config);
+    let exa = foreign("intruder", &["kill"]);
+    assert!(matches!(
+        host.receive_exa(exa),
+        Err(DeployError::ForbiddenInstruction(_))
+    ));
+}
+
+#[test]
+fn profiles_can_be_adjusted_per_host() {
+    let config = host_config(
+        "profile = \"sandboxed\"\n\
+         enabled_instructions = [\"prnt\"]\n\
+         disabled_instructions = [\"repl\"]",
+    )
+    .unwrap();
+    assert_eq!(
+        config.compiler_config.disabled_instructions,
+        ["kill", "link", "repl"]
+    );
+    let mut host = Host::from_config(config);
+    host.receive_exa(foreign("printer", &["prnt 1"])).unwrap();
+    let exa = foreign("spawner", &["repl a", "mark a"]);
+    assert!(matches!(
+        host.receive_exa(exa),
+        Err(DeployError::ForbiddenInstruction(_))
+    ));
+    assert_eq!(host.exa_count(), 1);
+}
+
+#[test]
+fn profiles_have_to_exist_and_replace_the_compiler_config() {
+    assert!(host_config("profile = \"lenient\"").is_err());
+    assert!(host_config("").is_err());
+    let both = "profile = \"sandboxed\"\n[compiler_config]\nextra_instructions = true";
+    assert!(host_config(both).is_err());
+}
+
+#[test]
+fn profiles_are_written_out_in_full() {
+    let config = host_config("profile = \"sandboxed\"").unwrap();
+    let written = toml::to_string(&config).unwrap();
+    let table: toml::Table = toml::from_str(&written).unwrap();
+    assert!(!table.contains_key("profile"));
+    let read: HostConfig = toml::from_str(&written).unwrap();
+    assert_eq!(read.compiler_config.disabled_instructions, ["kill", "link"]);
+}
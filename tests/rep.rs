@@ -0,0 +1,61 @@
+mod common;
+
+use common::{disassemble, error_types, errors};
+use exahost::compiler::ErrorType;
+
+#[test]
+fn nested_reps_count_per_level() {
+    let instructions = disassemble(&[
+        "@rep 2",
+        "@rep 3",
+        "addi @{0,1} @{10,10,1} x",
+        "@end",
+        "@end",
+    ]);
+    assert_eq!(
+        instructions,
+        [
+            "ADDI 0 10 X",
+            "ADDI 1 10 X",
+            "ADDI 2 10 X",
+            "ADDI 0 20 X",
+            "ADDI 1 20 X",
+            "ADDI 2 20 X",
+        ]
+    );
+}
+
+#[test]
+fn nested_reps_expand_instructions_between_them() {
+    let instructions = disassemble(&["@rep 2", "noop", "@rep 2", "halt", "@end", "@end"]);
+    assert_eq!(
+        instructions,
+        ["NOOP", "HALT", "HALT", "NOOP", "HALT", "HALT"]
+    );
+}
+
+#[test]
+fn counter_levels_past_the_nesting_are_errors() {
+    assert_eq!(
+        error_types(&["@rep 2", "copy @{0,1,1} x", "@end"]),
+        [ErrorType::MacroLevelOutOfRange]
+    );
+}
+
+#[test]
+fn unbalanced_reps_are_errors() {
+    assert_eq!(
+        error_types(&["@rep 2", "@end", "@end"]),
+        [ErrorType::MissingRepTag]
+    );
+    assert_eq!(error_types(&["@rep 2", "noop"]), [ErrorType::MissingEndTag]);
+}
+
+#[test]
+fn negative_counts_are_errors() {
+    let errs = errors(&["noop", "@rep -1", "noop", "@end"]);
+    assert_eq!(errs.len(), 1);
+    assert_eq!(errs[0].etype, ErrorType::NumberOutOfRange);
+    assert_eq!((errs[0].row, errs[0].col), (1, 5));
+    assert!(disassemble(&["@rep 0", "noop", "@end"]).is_empty());
+}
@@ -0,0 +1,172 @@
+use exahost::compiler::{config::Config, Compiler, ErrorType};
+use exahost::solution::{MemoryMode, Score, Solution, SolutionError, SolutionExa};
+
+const COMPLETED: &str = "tests/fixtures/solutions/completed.solution";
+const UNFINISHED: &str = "tests/fixtures/solutions/unfinished.solution";
+
+#[test]
+fn reads_completed_solution() {
+    let solution = Solution::from_file(COMPLETED).unwrap();
+    assert_eq!(solution.puzzle, "PB000");
+    assert_eq!(solution.name, "NEW SOLUTION 1");
+    assert_eq!(
+        solution.score,
+        Some(Score {
+            cycles: 27,
+            size: 14,
+            activity: 2,
+        })
+    );
+
+    let names: Vec<&str> = solution.exas.iter().map(|e| &e.name[..]).collect();
+    assert_eq!(names, ["XA", "XB"]);
+    assert_eq!(solution.exas[0].mode, MemoryMode::Global);
+    assert_eq!(solution.exas[1].mode, MemoryMode::Local);
+    assert_eq!(solution.exas[0].lines()[0], "LINK 800");
+    assert_eq!(solution.exas[0].lines().len(), 10);
+    assert!(solution.exas[0].sprite[1]);
+    assert!(!solution.exas[0].sprite[0]);
+}
+
+#[test]
+fn reads_unfinished_solution() {
+    let solution = Solution::from_file(UNFINISHED).unwrap();
+    assert_eq!(solution.puzzle, "PB002");
+    assert_eq!(solution.score, None);
+    assert_eq!(solution.exas.len(), 1);
+    // longer than 127 bytes, so its length takes two bytes
+    assert_eq!(solution.exas[0].source.len(), 184);
+    assert_eq!(solution.exas[0].editor_view, 1);
+}
+
+#[test]
+fn writes_fixtures_back_unchanged() {
+    for path in [COMPLETED, UNFINISHED] {
+        let bytes = std::fs::read(path).unwrap();
+        let solution = Solution::from_bytes(&bytes).unwrap();
+        assert_eq!(solution.to_bytes(), bytes, "{}", path);
+    }
+}
+
+#[test]
+fn compiles_exas_with_game_sizes() {
+    let solution = Solution::from_file(COMPLETED).unwrap();
+    let exas = solution
+        .compile(&Compiler::new(Config::game_faithful()))
+        .unwrap();
+    let size: usize = exas.iter().map(|e| e.size).sum();
+    assert_eq!(exas[0].name, "XA");
+    assert_eq!(size as i32, solution.score.unwrap().size);
+}
+
+#[test]
+fn compile_errors_carry_exa_name() {
+    let solution = Solution::new(
+        "PB000",
+        "BROKEN",
+        vec![SolutionExa::new("XA", "JUMP NOWHERE")],
+    );
+    let errs = solution
+        .compile(&Compiler::new(Config::game_faithful()))
+        .unwrap_err();
+    assert_eq!(errs[0].file.as_deref(), Some("XA"));
+}
+
+#[test]
+fn exports_exahost_sources() {
+    let mut exa = SolutionExa::new("XA", "COPY 1 X\r\nHALT\r\n");
+    exa.mode = MemoryMode::Local;
+    let solution = Solution::new("PB000", "EXPORTED", vec![exa]);
+    let read = Solution::from_bytes(&solution.to_bytes()).unwrap();
+    assert_eq!(read, solution);
+    assert_eq!(read.exas[0].source, "COPY 1 X\nHALT");
+}
+
+#[test]
+fn rejects_malformed_files() {
+    let bytes = std::fs::read(COMPLETED).unwrap();
+    assert!(matches!(
+        Solution::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(SolutionError::UnexpectedEof)
+    ));
+    assert!(matches!(
+        Solution::from_bytes(&[&bytes[..], &[0]].concat()),
+        Err(SolutionError::TrailingData)
+    ));
+    let mut version = bytes.clone();
+    version[0] = 0;
+    assert!(matches!(
+        Solution::from_bytes(&version),
+        Err(SolutionError::UnsupportedVersion(_))
+    ));
+}
+
+#[test]
+fn refuses_names_that_escape_the_directory() {
+    assert_eq!(
+        SolutionExa::new("XA", "").file_name().as_deref(),
+        Some("XA.exa")
+    );
+    for name in ["", "..", "../XA", "A/B", r"A\B", "/etc/XA", "C:XA"] {
+        assert_eq!(SolutionExa::new(name, "").file_name(), None, "{:?}", name);
+    }
+}
+
+#[test]
+fn keeps_the_mode_in_sources() {
+    let mut exa = SolutionExa::new("XA", "COPY 1 X\nHALT");
+    exa.mode = MemoryMode::Local;
+    let source = exa.to_source();
+    assert_eq!(source, "@mode local\nCOPY 1 X\nHALT\n");
+    assert_eq!(SolutionExa::from_source("XA", &source), exa);
+
+    exa.mode = MemoryMode::Global;
+    assert_eq!(exa.to_source(), "COPY 1 X\nHALT\n");
+    assert_eq!(SolutionExa::from_source("XA", &exa.to_source()), exa);
+}
+
+#[test]
+fn compiles_mode_directives() {
+    let compiler = Compiler::new(Config::game_faithful());
+    let program = compiler
+        .compile_program(&["@mode LOCAL", "copy 1 x"])
+        .unwrap();
+    assert_eq!(program.metadata.mode, Some(MemoryMode::Local));
+    assert_eq!(program.len(), 1);
+
+    let errs = compiler
+        .compile_program(&["@mode shared", "copy 1 x"])
+        .unwrap_err();
+    assert_eq!(errs[0].etype, ErrorType::InvalidMode);
+    let errs = compiler
+        .compile_program(&["@mode local", "@mode global", "copy 1 x"])
+        .unwrap_err();
+    assert_eq!(errs[0].etype, ErrorType::DuplicateDirective);
+}
+
+#[test]
+fn compiles_exas_in_their_mode() {
+    let compiler = Compiler::new(Config::game_faithful());
+    let mut exa = SolutionExa::new("XA", "COPY 1 X");
+    assert_eq!(
+        exa.compile(&compiler).unwrap().metadata.mode,
+        Some(MemoryMode::Global)
+    );
+    exa.mode = MemoryMode::Local;
+    assert_eq!(
+        exa.compile(&compiler).unwrap().metadata.mode,
+        Some(MemoryMode::Local)
+    );
+
+    // exported, imported and compiled again
+    let imported = SolutionExa::from_source("XA", &exa.to_source());
+    assert_eq!(imported.mode, MemoryMode::Local);
+    assert_eq!(
+        imported.compile(&compiler).unwrap().metadata.mode,
+        Some(MemoryMode::Local)
+    );
+    let exported = compiler
+        .compile_program(&exa.to_source().lines().collect::<Vec<_>>())
+        .unwrap();
+    assert_eq!(exported.metadata.mode, Some(MemoryMode::Local));
+}
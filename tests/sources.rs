@@ -0,0 +1,74 @@
+mod common;
+
+use common::compiler;
+use exahost::compiler::ErrorType;
+
+const CRLF: &str = "tests/fixtures/sources/crlf.exa";
+
+#[test]
+fn strings_may_use_crlf_and_start_with_a_bom() {
+    let compiler = compiler();
+    let lf = compiler
+        .compile_str("COPY 1 X\nADDI X 1 X\nHALT\n")
+        .unwrap();
+    let crlf = std::fs::read_to_string(CRLF).unwrap();
+    assert!(crlf.starts_with('\u{feff}') && crlf.contains("\r\n"));
+    assert_eq!(compiler.compile_str(&crlf).unwrap(), lf);
+}
+
+#[test]
+fn readers_compile_like_strings() {
+    let compiler = compiler();
+    let file = std::fs::File::open(CRLF).unwrap();
+    let from_reader = compiler.compile_reader(file).unwrap();
+    assert_eq!(from_reader.len(), 3);
+    assert_eq!(compiler.compile_file(CRLF).unwrap(), from_reader);
+
+    let errs = compiler.compile_reader(&[0xff, 0xfe][..]).unwrap_err();
+    assert_eq!(errs[0].etype, ErrorType::UnreadableSource);
+}
+
+#[test]
+fn directories_compile_every_exa_file_in_order() {
+    let exas = compiler().compile_dir("tests/fixtures/sources").unwrap();
+    let names: Vec<&str> = exas.iter().map(|e| &e.name[..]).collect();
+    // named by file stem, unless `@name` says otherwise
+    assert_eq!(names, ["crlf", "WORKER"]);
+    assert_eq!(exas[0].instr_list.len(), 3);
+}
+
+#[test]
+fn errors_carry_the_file_they_are_in() {
+    let errs = compiler().compile_dir("tests/fixtures/broken").unwrap_err();
+    let found: Vec<(&str, usize, ErrorType)> = errs
+        .iter()
+        .map(|e| (e.file.as_deref().unwrap(), e.row, e.etype))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (
+                "tests/fixtures/broken/first.exa",
+                1,
+                ErrorType::UnknownInstruction
+            ),
+            (
+                "tests/fixtures/broken/second.exa",
+                1,
+                ErrorType::UndefinedLabel
+            ),
+        ]
+    );
+
+    let errs = compiler()
+        .compile_dir("tests/fixtures/missing")
+        .unwrap_err();
+    assert_eq!(errs[0].etype, ErrorType::UnreadableSource);
+    assert_eq!(errs[0].file.as_deref(), Some("tests/fixtures/missing"));
+}
+
+#[test]
+fn sources_without_a_file_have_none() {
+    let errs = compiler().compile_str("BOGUS").unwrap_err();
+    assert_eq!(errs[0].file, None);
+}